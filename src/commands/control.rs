@@ -0,0 +1,9 @@
+use crate::{interfaces::control_socket, models::control_command::ControlCommand};
+
+pub fn send(command: ControlCommand) -> Result<(), Box<dyn std::error::Error>> {
+    if control_socket::send_command(&command)? == 0 {
+        return Err("no running waybar-module-music instance found".into());
+    }
+
+    Ok(())
+}
@@ -0,0 +1,182 @@
+use std::{error::Error, fs, path::PathBuf};
+
+use crate::{
+    helpers,
+    interfaces::{bus::Bus, dbus_client::DBusClient},
+    models::{
+        args::Args,
+        config::{Config, ConfigError},
+    },
+    services::display,
+};
+
+/// The outcome of a single check, with what was found either way
+type Check = Result<String, String>;
+
+fn check_bus(bus: &Bus) -> Check {
+    let conn = bus
+        .connect()
+        .map_err(|err| format!("can't connect to {bus}: {err}"))?;
+    Ok(format!("connected to {bus} as {}", conn.unique_name()))
+}
+
+fn check_players(bus: &Bus) -> Check {
+    let client = DBusClient::try_new(bus).map_err(|err| err.to_string())?;
+    let names = client.get_players().map_err(|err| err.to_string())?;
+
+    if names.is_empty() {
+        Ok("none running right now".to_string())
+    } else {
+        Ok(names.join(", "))
+    }
+}
+
+/// The 1-based line and column of a byte offset into `text`
+fn line_and_column(text: &str, offset: usize) -> (usize, usize) {
+    let before = text.get(..offset).unwrap_or(text);
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    (
+        before.matches('\n').count() + 1,
+        before[line_start..].chars().count() + 1,
+    )
+}
+
+fn check_config() -> Check {
+    match Config::new() {
+        Ok(_) => Ok("parsed".to_string()),
+        Err(ConfigError::InvalidType(err)) => {
+            let path = Config::path().map_err(|err| err.to_string())?;
+            let text = fs::read_to_string(&path).unwrap_or_default();
+            match err.span() {
+                Some(span) => {
+                    let (line, column) = line_and_column(&text, span.start);
+                    Err(format!(
+                        "{path:?} line {line}, column {column}: {}",
+                        err.message()
+                    ))
+                }
+                None => Err(format!("{path:?}: {}", err.message())),
+            }
+        }
+        Err(err) => Err(err.to_string()),
+    }
+}
+
+fn check_writable(dir: Result<PathBuf, std::io::Error>) -> Check {
+    let dir = dir.map_err(|err| err.to_string())?;
+    let probe = dir.join(".doctor");
+
+    fs::create_dir_all(&dir).map_err(|err| format!("can't create {dir:?}: {err}"))?;
+    fs::write(&probe, b"").map_err(|err| format!("{dir:?} is not writable: {err}"))?;
+    let _ = fs::remove_file(&probe);
+
+    Ok(format!("{dir:?}"))
+}
+
+/// Placeholders like "%artst%" in `format` that the module doesn't know,
+/// skipping lone percent signs like "100%"
+fn unknown_placeholders(format: &str) -> Vec<String> {
+    let mut unknown = Vec::new();
+    let mut rest = format;
+
+    while let Some(start) = rest.find('%') {
+        let after = &rest[start + 1..];
+        let Some(end) = after.find('%') else {
+            break;
+        };
+
+        let name = &after[..end];
+        let is_placeholder = !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if !is_placeholder {
+            // the closing percent sign may open the next placeholder
+            rest = &after[end..];
+            continue;
+        }
+
+        if !display::PLACEHOLDERS.contains(&name) {
+            unknown.push(format!("%{name}%"));
+        }
+        rest = &after[end + 1..];
+    }
+
+    unknown
+}
+
+fn check_format(format: &str) -> Check {
+    let unknown = unknown_placeholders(format);
+    if unknown.is_empty() {
+        Ok(format!("\"{format}\""))
+    } else {
+        Err(format!("\"{format}\" uses unknown {}", unknown.join(", ")))
+    }
+}
+
+/// Check what the module depends on, and print a report to paste into an issue
+pub fn run(args: &Args) -> Result<(), Box<dyn Error>> {
+    // a broken config.toml gets its own check below
+    let bus = Bus::new(args, Config::new().ok().as_ref());
+
+    let checks = [
+        ("bus", check_bus(&bus)),
+        ("MPRIS players", check_players(&bus)),
+        ("config.toml", check_config()),
+        (
+            "log directory",
+            check_writable(helpers::dir::get_and_create_dir(dirs::cache_dir)),
+        ),
+        (
+            "art cache directory",
+            check_writable(
+                helpers::dir::get_and_create_dir(dirs::cache_dir).map(|dir| dir.join("art")),
+            ),
+        ),
+        (
+            "history directory",
+            check_writable(helpers::dir::get_and_create_dir(dirs::data_dir)),
+        ),
+        (
+            "runtime directory",
+            check_writable(helpers::dir::get_and_create_dir(|| {
+                dirs::runtime_dir().or_else(dirs::cache_dir)
+            })),
+        ),
+        ("--format", check_format(&args.format)),
+        ("--player-format", check_format(&args.player_format)),
+    ];
+
+    println!("waybar-module-music {}", env!("CARGO_PKG_VERSION"));
+    for (name, check) in &checks {
+        match check {
+            Ok(found) => println!("[pass] {name}: {found}"),
+            Err(err) => println!("[FAIL] {name}: {err}"),
+        }
+    }
+
+    let failed = checks.iter().filter(|(_, check)| check.is_err()).count();
+    if failed > 0 {
+        return Err(format!("{failed} of {} checks failed", checks.len()).into());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn offsets_become_lines_and_columns_counted_from_one() {
+        let text = "[history]\nenabled = yes\n";
+        assert_eq!(line_and_column(text, 0), (1, 1));
+        assert_eq!(line_and_column(text, text.find("yes").unwrap()), (2, 11));
+    }
+
+    #[test]
+    fn columns_count_characters_rather_than_bytes() {
+        let text = "name = \"Beyoncé\" x";
+        assert_eq!(line_and_column(text, text.find('x').unwrap()), (1, 18));
+    }
+}
@@ -0,0 +1,82 @@
+use chrono::{Local, TimeZone};
+
+use crate::{
+    interfaces::play_store::PlayStore,
+    models::{args::ExportFormat, play::Play},
+    utils::time::microseconds_to_formatted_time,
+};
+
+pub fn show(limit: usize, export: Option<ExportFormat>) -> Result<(), Box<dyn std::error::Error>> {
+    let store = PlayStore::history()?;
+    let plays = store.read_all()?;
+
+    let recent = &plays[plays.len().saturating_sub(limit)..];
+
+    match export {
+        Some(ExportFormat::Csv) => print_csv(recent),
+        Some(ExportFormat::M3u) => print_m3u(recent),
+        None if recent.is_empty() => println!(
+            "no plays recorded yet, enable [history] in config.toml to start recording to {:?}",
+            store.path()
+        ),
+        None => print_table(recent),
+    }
+
+    Ok(())
+}
+
+fn format_timestamp(timestamp: u64) -> String {
+    match Local.timestamp_opt(timestamp as i64, 0).single() {
+        Some(time) => time.format("%Y-%m-%d %H:%M").to_string(),
+        None => timestamp.to_string(),
+    }
+}
+
+fn print_table(plays: &[Play]) {
+    // newest first, which is what you want to see when glancing at it
+    for play in plays.iter().rev() {
+        println!(
+            "{}  {:>8}  {} - {} ({})  [{}]",
+            format_timestamp(play.timestamp),
+            microseconds_to_formatted_time(play.listened as u128 * 1000 * 1000),
+            play.artist,
+            play.title,
+            play.album,
+            play.player
+        );
+    }
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn print_csv(plays: &[Play]) {
+    println!("timestamp,player,artist,title,album,length,listened,url");
+    for play in plays {
+        println!(
+            "{},{},{},{},{},{},{},{}",
+            play.timestamp,
+            csv_field(&play.player),
+            csv_field(&play.artist),
+            csv_field(&play.title),
+            csv_field(&play.album),
+            play.length,
+            play.listened,
+            csv_field(&play.url)
+        );
+    }
+}
+
+/// Plays without a URL can't be played back, so they're left out of the playlist
+fn print_m3u(plays: &[Play]) {
+    println!("#EXTM3U");
+    for play in plays.iter().filter(|play| !play.url.is_empty()) {
+        println!("#EXTINF:{},{} - {}", play.length, play.artist, play.title);
+        println!("{}", play.url);
+    }
+}
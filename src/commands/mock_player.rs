@@ -0,0 +1,344 @@
+use std::{
+    error::Error,
+    fs,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use dbus::{
+    arg::{PropMap, Variant},
+    blocking::{
+        stdintf::org_freedesktop_dbus::{PropertiesPropertiesChanged, RequestNameReply},
+        Connection,
+    },
+    channel::Sender,
+    message::{MessageType, SignalArgs},
+    Message,
+};
+use dbus_crossroads::{Crossroads, IfaceBuilder, IfaceToken};
+
+use crate::{
+    interfaces::bus::Bus,
+    models::{
+        mock_track::{MockScript, MockTrack},
+        playback_state::PlaybackState,
+    },
+};
+
+const OBJECT_PATH: &str = "/org/mpris/MediaPlayer2";
+const ROOT_INTERFACE: &str = "org.mpris.MediaPlayer2";
+const PLAYER_INTERFACE: &str = "org.mpris.MediaPlayer2.Player";
+
+/// A player going through its tracks, as the D-Bus interfaces see it
+struct MockPlayer {
+    name: String,
+    tracks: Vec<MockTrack>,
+    step: Duration,
+    index: usize,
+    status: PlaybackState,
+    rate: f64,
+    /// Position in microseconds at `since`, which moves on from there while playing
+    position: i64,
+    since: Instant,
+    /// A position to announce with `Seeked`, after a seek
+    seeked: Option<i64>,
+}
+
+/// What other programs are told about when it changes
+#[derive(PartialEq, Clone)]
+struct Announced {
+    index: usize,
+    status: PlaybackState,
+    rate: f64,
+}
+
+impl MockPlayer {
+    fn new(name: String, tracks: Vec<MockTrack>, step: Duration) -> Self {
+        let rate = tracks.first().and_then(|t| t.rate).unwrap_or(1.0);
+        Self {
+            name,
+            tracks,
+            step,
+            index: 0,
+            status: PlaybackState::Playing,
+            rate,
+            position: 0,
+            since: Instant::now(),
+            seeked: None,
+        }
+    }
+
+    fn track(&self) -> &MockTrack {
+        &self.tracks[self.index]
+    }
+
+    fn position(&self) -> i64 {
+        match self.status {
+            PlaybackState::Playing => {
+                self.position + (self.since.elapsed().as_micros() as f64 * self.rate) as i64
+            }
+            _ => self.position,
+        }
+    }
+
+    fn set_position(&mut self, position: i64) {
+        self.position = position.max(0);
+        self.since = Instant::now();
+    }
+
+    fn set_status(&mut self, status: PlaybackState) {
+        // keep the position where it is, since it only moves on while playing
+        self.set_position(self.position());
+        if status == PlaybackState::Stopped {
+            self.position = 0;
+        }
+        self.status = status;
+    }
+
+    fn go_to(&mut self, index: usize) {
+        self.index = index % self.tracks.len();
+        self.rate = self.track().rate.unwrap_or(1.0);
+        self.set_position(0);
+
+        let track = self.track();
+        println!(
+            "track {}/{}: {} - {}",
+            self.index + 1,
+            self.tracks.len(),
+            track.artist.as_deref().unwrap_or_default(),
+            track.title.as_deref().unwrap_or_default()
+        );
+    }
+
+    fn seek_to(&mut self, position: i64) {
+        self.set_position(position);
+        self.seeked = Some(self.position);
+    }
+
+    /// How long the current track plays before the script moves on, in microseconds
+    fn play_for(&self) -> i64 {
+        let track = self.track();
+        let play_for = track.play_for.map(Duration::from_secs).unwrap_or(self.step);
+        let play_for = play_for.as_micros() as i64;
+
+        match track.length {
+            Some(length) => play_for.min(length as i64 * 1000 * 1000),
+            None => play_for,
+        }
+    }
+
+    fn status_name(&self) -> String {
+        match self.status {
+            PlaybackState::Playing => String::from("Playing"),
+            PlaybackState::Paused => String::from("Paused"),
+            PlaybackState::Stopped => String::from("Stopped"),
+        }
+    }
+
+    fn announced(&self) -> Announced {
+        Announced {
+            index: self.index,
+            status: self.status.clone(),
+            rate: self.rate,
+        }
+    }
+}
+
+fn register_root(cr: &mut Crossroads) -> IfaceToken<MockPlayer> {
+    cr.register(ROOT_INTERFACE, |b: &mut IfaceBuilder<MockPlayer>| {
+        b.property("Identity")
+            .get(|_, player| Ok(player.name.clone()));
+        b.property("DesktopEntry")
+            .get(|_, player| Ok(player.name.clone()));
+        b.property("CanQuit").get(|_, _| Ok(false));
+        b.property("CanRaise").get(|_, _| Ok(false));
+        b.property("HasTrackList").get(|_, _| Ok(false));
+        b.property("SupportedUriSchemes")
+            .get(|_, _| Ok(Vec::<String>::new()));
+        b.property("SupportedMimeTypes")
+            .get(|_, _| Ok(Vec::<String>::new()));
+        b.method("Raise", (), (), |_, _, ()| Ok(()));
+        b.method("Quit", (), (), |_, _, ()| Ok(()));
+    })
+}
+
+fn register_player(cr: &mut Crossroads) -> IfaceToken<MockPlayer> {
+    cr.register(PLAYER_INTERFACE, |b: &mut IfaceBuilder<MockPlayer>| {
+        // changes are announced by hand after every call, see `announce_changes`
+        b.property("PlaybackStatus")
+            .get(|_, player| Ok(player.status_name()));
+        b.property("Metadata")
+            .get(|_, player| Ok(player.track().metadata(player.index)));
+        b.property("Position")
+            .emits_changed_false()
+            .get(|_, player| Ok(player.position()));
+        b.property("Rate").get(|_, player| Ok(player.rate));
+        b.property("MinimumRate").get(|_, _| Ok(0.5));
+        b.property("MaximumRate").get(|_, _| Ok(2.0));
+        b.property("Volume").get(|_, _| Ok(1.0));
+        for capability in [
+            "CanControl",
+            "CanPlay",
+            "CanPause",
+            "CanGoNext",
+            "CanGoPrevious",
+            "CanSeek",
+        ] {
+            b.property(capability).get(|_, _| Ok(true));
+        }
+
+        b.method("Play", (), (), |_, player, ()| {
+            player.set_status(PlaybackState::Playing);
+            Ok(())
+        });
+        b.method("Pause", (), (), |_, player, ()| {
+            player.set_status(PlaybackState::Paused);
+            Ok(())
+        });
+        b.method("PlayPause", (), (), |_, player, ()| {
+            let status = match player.status {
+                PlaybackState::Playing => PlaybackState::Paused,
+                _ => PlaybackState::Playing,
+            };
+            player.set_status(status);
+            Ok(())
+        });
+        b.method("Stop", (), (), |_, player, ()| {
+            player.set_status(PlaybackState::Stopped);
+            Ok(())
+        });
+        b.method("Next", (), (), |_, player, ()| {
+            player.go_to(player.index + 1);
+            Ok(())
+        });
+        b.method("Previous", (), (), |_, player, ()| {
+            player.go_to(player.index + player.tracks.len() - 1);
+            Ok(())
+        });
+        b.method("Seek", ("Offset",), (), |_, player, (offset,): (i64,)| {
+            player.seek_to(player.position() + offset);
+            Ok(())
+        });
+        b.method(
+            "SetPosition",
+            ("TrackId", "Position"),
+            (),
+            |_, player, (_, position): (dbus::Path<'static>, i64)| {
+                player.seek_to(position);
+                Ok(())
+            },
+        );
+        b.method("OpenUri", ("Uri",), (), |_, _, (_,): (String,)| Ok(()));
+    })
+}
+
+fn announce_changes(conn: &Connection, player: &MockPlayer, previous: &Announced) {
+    let mut changed = PropMap::new();
+    if previous.index != player.index {
+        changed.insert(
+            "Metadata".to_string(),
+            Variant(Box::new(player.track().metadata(player.index))),
+        );
+    }
+    if previous.status != player.status {
+        changed.insert(
+            "PlaybackStatus".to_string(),
+            Variant(Box::new(player.status_name())),
+        );
+    }
+    if previous.rate != player.rate {
+        changed.insert("Rate".to_string(), Variant(Box::new(player.rate)));
+    }
+
+    if !changed.is_empty() {
+        let signal = PropertiesPropertiesChanged {
+            interface_name: PLAYER_INTERFACE.to_string(),
+            changed_properties: changed,
+            invalidated_properties: vec![],
+        };
+        if conn
+            .send(signal.to_emit_message(&OBJECT_PATH.into()))
+            .is_err()
+        {
+            eprintln!("failed to send PropertiesChanged signal");
+        }
+    }
+}
+
+fn announce_seek(conn: &Connection, position: i64) {
+    match Message::new_signal(OBJECT_PATH, PLAYER_INTERFACE, "Seeked") {
+        Ok(signal) => {
+            if conn.send(signal.append1(position)).is_err() {
+                eprintln!("failed to send Seeked signal");
+            }
+        }
+        Err(err) => eprintln!("failed to create Seeked signal: {err}"),
+    }
+}
+
+fn read_script(path: &Path) -> Result<Vec<MockTrack>, Box<dyn Error>> {
+    let script: MockScript = toml::from_str(&fs::read_to_string(path)?)?;
+    if script.tracks.is_empty() {
+        return Err(format!("{path:?} has no [[tracks]]").into());
+    }
+    Ok(script.tracks)
+}
+
+/// Play through the tracks as `org.mpris.MediaPlayer2.<name>`, until stopped with Ctrl+C
+pub fn run(name: &str, script: Option<&Path>, step: u64, bus: &Bus) -> Result<(), Box<dyn Error>> {
+    let tracks = match script {
+        Some(path) => read_script(path)?,
+        None => MockTrack::defaults(),
+    };
+
+    let bus_name = format!("org.mpris.MediaPlayer2.{name}");
+    let conn = bus.connect()?;
+    if conn.request_name(&bus_name, false, false, true)? != RequestNameReply::PrimaryOwner {
+        return Err(format!("'{bus_name}' is already taken").into());
+    }
+    println!("playing as '{bus_name}' on {}", conn.unique_name());
+
+    let mut cr = Crossroads::new();
+    let root = register_root(&mut cr);
+    let player_iface = register_player(&mut cr);
+    let mut player = MockPlayer::new(name.to_string(), tracks, Duration::from_secs(step));
+    player.go_to(0);
+    cr.insert(OBJECT_PATH, &[root, player_iface], player);
+
+    loop {
+        if conn
+            .channel()
+            .read_write(Some(Duration::from_millis(100)))
+            .is_err()
+        {
+            return Err("lost the D-Bus connection".into());
+        }
+
+        let Some(previous) = cr
+            .data_mut::<MockPlayer>(&OBJECT_PATH.into())
+            .map(|player| player.announced())
+        else {
+            return Err("our object path disappeared".into());
+        };
+
+        while let Some(msg) = conn.channel().pop_message() {
+            if msg.msg_type() == MessageType::MethodCall {
+                let _ = cr.handle_message(msg, &conn);
+            }
+        }
+
+        let Some(player) = cr.data_mut::<MockPlayer>(&OBJECT_PATH.into()) else {
+            return Err("our object path disappeared".into());
+        };
+
+        // the script moves on by itself, like a player reaching the end of a track
+        if player.status == PlaybackState::Playing && player.position() >= player.play_for() {
+            player.go_to(player.index + 1);
+        }
+
+        if let Some(position) = player.seeked.take() {
+            announce_seek(&conn, position);
+        }
+        announce_changes(&conn, player, &previous);
+    }
+}
@@ -0,0 +1,41 @@
+use std::sync::Arc;
+
+use crate::{
+    interfaces::bus::Bus,
+    models::{
+        args::{Args, Command, PlayerAction},
+        config::Config,
+        control_command::ControlCommand,
+    },
+};
+
+pub mod control;
+pub mod doctor;
+pub mod history;
+pub mod mock_player;
+pub mod once;
+pub mod players;
+pub mod preview;
+pub mod stats;
+
+/// Run a subcommand, which is mostly a one-off rather than the module itself
+pub fn run(command: &Command, args: &Arc<Args>) -> Result<(), Box<dyn std::error::Error>> {
+    match command {
+        Command::Pin => control::send(ControlCommand::Pin),
+        Command::Unpin => control::send(ControlCommand::Unpin),
+        Command::Player { action } => match action {
+            PlayerAction::Next => control::send(ControlCommand::NextPlayer),
+            PlayerAction::Prev => control::send(ControlCommand::PreviousPlayer),
+        },
+        Command::Doctor => doctor::run(args),
+        Command::MockPlayer { name, script, step } => {
+            let bus = Bus::new(args, Config::new().ok().as_ref());
+            mock_player::run(name, script.as_deref(), *step, &bus)
+        }
+        Command::Players { json } => players::show(args, *json),
+        Command::History { limit, export } => history::show(*limit, *export),
+        Command::Stats { period, limit } => stats::show(*period, *limit),
+        // both run the module's services, just with another output
+        Command::Daemon | Command::Preview { .. } => crate::run_module(args.clone()),
+    }
+}
@@ -0,0 +1,94 @@
+use std::{collections::HashMap, error::Error, sync::Arc};
+
+use log::warn;
+
+use crate::{
+    event_bus::EventBus,
+    interfaces::{bus::Bus, dbus_client::DBusClient},
+    models::{
+        args::Args, config::Config, now_playing::NowPlaying, player_client::PlayerClient,
+        player_state::PlayerState,
+    },
+    selection,
+    services::{display::Display, mpris_proxy},
+};
+
+/// Every player on the bus that `--whitelist` allows, keyed by their unique bus name
+/// like the players PlayerManager learns about from their signals
+pub fn discover_players(args: &Args, dbus_client: &DBusClient) -> HashMap<String, PlayerClient> {
+    let names = match dbus_client.get_players() {
+        Ok(names) => names,
+        Err(err) => {
+            warn!("failed to list players: {err}");
+            return HashMap::new();
+        }
+    };
+
+    names
+        .iter()
+        // the proxy mirrors one of the others
+        .filter(|name| name.as_str() != mpris_proxy::BUS_NAME)
+        .filter_map(|name| {
+            let id = dbus_client
+                .query_name_owner(name)
+                .unwrap_or_else(|_| name.clone());
+            match dbus_client.query_player(&id) {
+                Ok(player) => Some((id, player)),
+                Err(err) => {
+                    warn!("failed to query player '{name}': {err}");
+                    None
+                }
+            }
+        })
+        .filter(|(_, player)| args.is_whitelisted(player.name()))
+        .collect()
+}
+
+/// The players the module would display right now, the displayed one first
+pub fn current_states(args: &Args, dbus_client: &DBusClient) -> Vec<PlayerState> {
+    let players = discover_players(args, dbus_client);
+
+    let policy = selection::from_args(args);
+    let ranked = if args.multi_player {
+        policy.rank(&players, None)
+    } else {
+        policy.select(&players, None).into_iter().collect()
+    };
+
+    ranked
+        .iter()
+        .filter_map(|id| players.get(id))
+        .filter_map(|player| {
+            PlayerState::from_mpris_data(
+                player.name().to_owned(),
+                player.metadata(),
+                player.playback_state(),
+                player.position(),
+            )
+        })
+        .collect()
+}
+
+/// Print what the module would display right now, then exit
+pub fn print(args: Arc<Args>) -> Result<(), Box<dyn Error>> {
+    let config = Arc::new(Config::new()?);
+    let dbus_client = DBusClient::try_new(&Bus::new(&args, Some(&config)))?;
+    let states = current_states(&args, &dbus_client);
+
+    if args.json {
+        let now_playing: Vec<NowPlaying> = states.iter().map(NowPlaying::from).collect();
+        let json = if args.multi_player {
+            serde_json::to_string(&now_playing)?
+        } else {
+            serde_json::to_string(&now_playing.first())?
+        };
+        println!("{json}");
+        return Ok(());
+    }
+
+    // nothing is published when printing once, the bus is only there because Display expects one
+    let (_, event_bus) = EventBus::new();
+    Display::new(args, config, event_bus).print_once(&states);
+
+    Ok(())
+}
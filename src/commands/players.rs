@@ -0,0 +1,115 @@
+use std::error::Error;
+
+use dbus::arg::{prop_cast, PropMap};
+
+use crate::{
+    interfaces::{bus::Bus, dbus_client::DBusClient},
+    models::{args::Args, config::Config, player_info::PlayerInfo},
+    services::mpris_proxy,
+};
+
+const CAPABILITIES: [&str; 6] = [
+    "CanControl",
+    "CanPlay",
+    "CanPause",
+    "CanGoNext",
+    "CanGoPrevious",
+    "CanSeek",
+];
+
+fn string_prop(properties: &PropMap, name: &str) -> String {
+    prop_cast::<String>(properties, name)
+        .cloned()
+        .unwrap_or_default()
+}
+
+fn inspect(
+    args: &Args,
+    config: &Config,
+    dbus_client: &DBusClient,
+    bus_name: &str,
+) -> Result<PlayerInfo, Box<dyn Error>> {
+    let unique_name = dbus_client.query_name_owner(bus_name)?;
+    let root = dbus_client.query_properties(bus_name, "org.mpris.MediaPlayer2")?;
+    let player = dbus_client.query_properties(bus_name, "org.mpris.MediaPlayer2.Player")?;
+    let metadata = dbus_client.query_metadata(bus_name)?;
+
+    let identity = string_prop(&root, "Identity");
+    let icon_rule = config.find_player_icon_rule(&identity);
+
+    Ok(PlayerInfo {
+        bus_name: bus_name.to_string(),
+        unique_name,
+        desktop_entry: string_prop(&root, "DesktopEntry"),
+        status: string_prop(&player, "PlaybackStatus"),
+        artist: metadata.artist.join(", "),
+        title: metadata.title.unwrap_or_default(),
+        capabilities: CAPABILITIES
+            .iter()
+            .filter(|name| prop_cast::<bool>(&player, name).copied().unwrap_or(false))
+            .map(|name| name.to_string())
+            .collect(),
+        whitelisted: args.is_whitelisted(&identity),
+        proxy: bus_name == mpris_proxy::BUS_NAME,
+        icon_rule: icon_rule.map(|(key, _)| key.clone()),
+        icon: config.get_player_icon_by_partial_match(&identity).clone(),
+        identity,
+    })
+}
+
+fn print_table(players: &[PlayerInfo]) {
+    for player in players {
+        let handled = if player.proxy {
+            "no, this is our own proxy of the active player"
+        } else if player.whitelisted {
+            "yes"
+        } else {
+            "no, filtered out by --whitelist"
+        };
+        let icon_rule = match &player.icon_rule {
+            Some(key) => format!("[icons.players] \"{key}\""),
+            None => "default".to_string(),
+        };
+
+        println!("{} ({})", player.bus_name, player.unique_name);
+        println!("  identity       {}", player.identity);
+        println!("  desktop entry  {}", player.desktop_entry);
+        println!("  status         {}", player.status);
+        println!("  track          {} - {}", player.artist, player.title);
+        println!("  capabilities   {}", player.capabilities.join(" "));
+        println!("  handled        {handled}");
+        println!("  icon           {}  from {icon_rule}", player.icon);
+        println!();
+    }
+}
+
+/// Print every MPRIS player on the bus, along with what the module makes of it
+pub fn show(args: &Args, json: bool) -> Result<(), Box<dyn Error>> {
+    let config = Config::new()?;
+    let bus = Bus::new(args, Some(&config));
+    let dbus_client = DBusClient::try_new(&bus)?;
+
+    let mut names = dbus_client.get_players()?;
+    names.sort();
+
+    let players: Vec<PlayerInfo> = names
+        .iter()
+        .filter_map(|name| match inspect(args, &config, &dbus_client, name) {
+            Ok(info) => Some(info),
+            Err(err) => {
+                eprintln!("failed to query player '{name}': {err}");
+                None
+            }
+        })
+        .collect();
+
+    if json {
+        println!("{}", serde_json::to_string(&players)?);
+    } else if players.is_empty() {
+        println!("no MPRIS players on {bus}");
+    } else {
+        print_table(&players);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,59 @@
+use bincode::config;
+use log::warn;
+
+use crate::{
+    event_bus::{EventBusHandle, EventType},
+    models::{
+        args::{Args, Command},
+        playback_state::PlaybackState,
+        player_state::PlayerState,
+    },
+};
+
+/// The track given on the command line, if the preview shouldn't follow the real players
+pub fn fake_track(args: &Args) -> Option<PlayerState> {
+    let Some(Command::Preview {
+        title,
+        artist,
+        album,
+        player,
+        length,
+    }) = &args.command
+    else {
+        return None;
+    };
+
+    if title.is_none() && artist.is_none() && album.is_none() {
+        return None;
+    }
+
+    Some(PlayerState::new(
+        String::from("preview"),
+        player.clone(),
+        artist.clone().unwrap_or_default(),
+        album.clone().unwrap_or_default(),
+        title.clone().unwrap_or_default(),
+        Some(PlaybackState::Playing),
+        length * 1000 * 1000,
+        0,
+    ))
+}
+
+/// Hands the display its players before it starts, as PlayerManager would have
+pub fn publish(args: &Args, event_bus: &EventBusHandle, states: Vec<PlayerState>) {
+    let Some(displayed) = states.first() else {
+        return;
+    };
+
+    match bincode::encode_to_vec(displayed, config::standard()) {
+        Ok(encoded) => event_bus.publish_retained(EventType::PlayerStateChanged, encoded),
+        Err(err) => warn!("failed to encode player state for the preview: {err}"),
+    }
+
+    if args.multi_player {
+        match bincode::encode_to_vec(&states, config::standard()) {
+            Ok(encoded) => event_bus.publish_retained(EventType::PlayerListChanged, encoded),
+            Err(err) => warn!("failed to encode player list for the preview: {err}"),
+        }
+    }
+}
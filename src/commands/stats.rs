@@ -0,0 +1,101 @@
+use std::collections::HashMap;
+
+use chrono::{Local, TimeZone};
+
+use crate::{
+    interfaces::play_store::PlayStore,
+    models::{args::StatsPeriod, listening_stats::ListeningStats, play::Play},
+    utils::time::{seconds_to_formatted_duration, start_of_local_day},
+};
+
+pub fn show(period: StatsPeriod, limit: usize) -> Result<(), Box<dyn std::error::Error>> {
+    let since = start_of_local_day(period.days() - 1);
+    let plays: Vec<Play> = PlayStore::history()?
+        .read_all()?
+        .into_iter()
+        .filter(|play| play.timestamp >= since)
+        .collect();
+
+    let since_date = Local
+        .timestamp_opt(since as i64, 0)
+        .single()
+        .map(|time| time.format("%Y-%m-%d").to_string())
+        .unwrap_or_default();
+    let total = ListeningStats::from_plays(&plays);
+    println!(
+        "since {since_date}: {} tracks, {} listened",
+        total.tracks,
+        seconds_to_formatted_duration(total.listened)
+    );
+
+    if plays.is_empty() {
+        return Ok(());
+    }
+
+    print_top(
+        "Top artists",
+        group_by(&plays, |play| Some(play.artist.clone())),
+        limit,
+    );
+    print_top(
+        "Top albums",
+        group_by(&plays, |play| {
+            (!play.album.is_empty()).then(|| format!("{} - {}", play.artist, play.album))
+        }),
+        limit,
+    );
+    print_top(
+        "Top tracks",
+        group_by(&plays, |play| {
+            Some(format!("{} - {}", play.artist, play.title))
+        }),
+        limit,
+    );
+
+    println!("\nListening time per player");
+    let mut players = group_by(&plays, |play| Some(play.player.clone()));
+    players.sort_by(|(a_name, a), (b_name, b)| {
+        b.listened.cmp(&a.listened).then_with(|| a_name.cmp(b_name))
+    });
+    for (player, stats) in players {
+        println!(
+            "  {:>8}  {player}",
+            seconds_to_formatted_duration(stats.listened)
+        );
+    }
+
+    Ok(())
+}
+
+/// Sum up the plays sharing the same key, skipping the ones without one
+fn group_by(
+    plays: &[Play],
+    key: impl Fn(&Play) -> Option<String>,
+) -> Vec<(String, ListeningStats)> {
+    let mut groups: HashMap<String, ListeningStats> = HashMap::new();
+    for play in plays {
+        if let Some(key) = key(play).filter(|key| !key.trim().is_empty()) {
+            groups.entry(key).or_default().add(play);
+        }
+    }
+    groups.into_iter().collect()
+}
+
+fn print_top(heading: &str, mut groups: Vec<(String, ListeningStats)>, limit: usize) {
+    groups.sort_by(|(a_name, a), (b_name, b)| {
+        b.tracks
+            .cmp(&a.tracks)
+            .then_with(|| b.listened.cmp(&a.listened))
+            .then_with(|| a_name.cmp(b_name))
+    });
+
+    println!("\n{heading}");
+    for (i, (name, stats)) in groups.iter().take(limit).enumerate() {
+        println!(
+            "  {:>2}. {name} ({} plays, {})",
+            i + 1,
+            stats.tracks,
+            seconds_to_formatted_duration(stats.listened)
+        );
+    }
+}
@@ -1,15 +1,22 @@
 use std::{collections::HashMap, fmt::Display, sync::mpsc};
 
+use bincode::{Decode, Encode};
 use log::{debug, error, info, warn};
 
-#[derive(Debug, Eq, Hash, PartialEq, Clone)]
+#[derive(Debug, Eq, Hash, PartialEq, Clone, Encode, Decode)]
 pub enum EventType {
     PlayerStateChanged,
+    PlayerListChanged,
     PlayerSongChanged,
     PlaybackChanged,
     Seeked,
     Rate,
     Identity,
+    ControlCommand,
+    ListeningStatsChanged,
+    ArtCached,
+    LyricsLoaded,
+    BusStatusChanged,
     ParseError,
     Unknown(String),
 }
@@ -21,11 +28,17 @@ impl Display for EventType {
             "{}",
             match self {
                 EventType::PlayerStateChanged => "PlayerStateChanged",
+                EventType::PlayerListChanged => "PlayerListChanged",
                 EventType::PlayerSongChanged => "PlayerSongChanged",
                 EventType::PlaybackChanged => "PlaybackChanged",
                 EventType::Seeked => "Seeked",
                 EventType::Rate => "Rate",
                 EventType::Identity => "Identity",
+                EventType::ControlCommand => "ControlCommand",
+                EventType::ListeningStatsChanged => "ListeningStatsChanged",
+                EventType::ArtCached => "ArtCached",
+                EventType::LyricsLoaded => "LyricsLoaded",
+                EventType::BusStatusChanged => "BusStatusChanged",
                 EventType::ParseError => "ParseError",
                 EventType::Unknown(_) => "Unknown",
             }
@@ -37,6 +50,8 @@ pub enum EventBusMessage {
     Publish {
         event_type: EventType,
         data: Vec<u8>,
+        /// Whether subscribers that come later still get this, until something newer is published
+        retain: bool,
     },
     Subscribe {
         event_type: EventType,
@@ -50,6 +65,7 @@ impl Display for EventBusMessage {
             EventBusMessage::Publish {
                 event_type,
                 data: _,
+                retain: _,
             } => ("Publish", event_type),
             EventBusMessage::Subscribe {
                 event_type,
@@ -67,7 +83,20 @@ pub struct EventBusHandle {
 
 impl EventBusHandle {
     pub fn publish(&self, event_type: EventType, data: Vec<u8>) {
-        let msg = EventBusMessage::Publish { event_type, data };
+        self.send_publish(event_type, data, false);
+    }
+
+    /// Like `publish`, but also delivered to whoever subscribes later
+    pub fn publish_retained(&self, event_type: EventType, data: Vec<u8>) {
+        self.send_publish(event_type, data, true);
+    }
+
+    fn send_publish(&self, event_type: EventType, data: Vec<u8>, retain: bool) {
+        let msg = EventBusMessage::Publish {
+            event_type,
+            data,
+            retain,
+        };
         if let Err(err) = self.tx.send(msg) {
             error!("failed to publish message on bus: {err}");
         }
@@ -92,6 +121,7 @@ impl EventBusHandle {
 pub struct EventBus {
     rx: mpsc::Receiver<EventBusMessage>,
     senders: HashMap<EventType, Vec<mpsc::Sender<Vec<u8>>>>,
+    retained: HashMap<EventType, Vec<u8>>,
 }
 
 impl EventBus {
@@ -101,6 +131,7 @@ impl EventBus {
         let bus = Self {
             rx,
             senders: HashMap::new(),
+            retained: HashMap::new(),
         };
 
         let handle = EventBusHandle { tx };
@@ -112,15 +143,27 @@ impl EventBus {
         while let Ok(msg) = self.rx.recv() {
             debug!("{msg}");
             match msg {
-                EventBusMessage::Publish { event_type, data } => {
-                    match self.senders.get(&event_type) {
+                EventBusMessage::Publish {
+                    event_type,
+                    data,
+                    retain,
+                } => {
+                    if retain {
+                        self.retained.insert(event_type.clone(), data.clone());
+                    }
+
+                    match self.senders.get_mut(&event_type) {
                         Some(senders) => {
-                            for sender in senders {
-                                if let Err(err) = sender.send(data.clone()) {
-                                    error!("failed to send data to subscribers: {err}");
+                            // subscribers go away when their service starts over, e.g. after reconnecting
+                            senders.retain(|sender| match sender.send(data.clone()) {
+                                Ok(()) => true,
+                                Err(err) => {
+                                    debug!("dropping '{event_type}' subscriber: {err}");
+                                    false
                                 }
-                            }
+                            });
                         }
+                        None if retain => (),
                         None => {
                             warn!("tried to get subscriber with type '{event_type}' but none found")
                         }
@@ -131,6 +174,9 @@ impl EventBus {
                     response_tx,
                 } => {
                     let (tx, rx) = mpsc::channel();
+                    if let Some(data) = self.retained.get(&event_type) {
+                        let _ = tx.send(data.clone());
+                    }
                     self.senders.entry(event_type).or_default().push(tx);
                     if let Err(err) = response_tx.send(rx) {
                         error!("failed to send receiver: {err}");
@@ -12,7 +12,7 @@ where
         })?
         .join("waybar-module-music");
 
-    let _ = fs::create_dir(&directory);
+    let _ = fs::create_dir_all(&directory);
 
     Ok(directory)
 }
@@ -0,0 +1,39 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    process,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+/// Tells apart the temporary files of concurrent writes within this process
+static TMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// A temporary path next to `path` that no other write uses, to be renamed over it.
+/// Every bar may run its own module, so a fixed name would race between them
+pub fn tmp_path(path: &Path) -> PathBuf {
+    let mut tmp_name = path.file_name().unwrap_or_default().to_os_string();
+    tmp_name.push(format!(
+        ".{}.{}.tmp",
+        process::id(),
+        TMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    path.with_file_name(tmp_name)
+}
+
+/// Write through a temporary file next to `path` and rename it into place,
+/// so readers never see a half written file, even if we crash midway
+pub fn write_atomically(path: &Path, contents: &[u8]) -> Result<(), std::io::Error> {
+    let tmp_path = tmp_path(path);
+
+    let written = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&tmp_path)
+        .and_then(|mut file| file.write_all(contents));
+    if let Err(err) = written {
+        let _ = fs::remove_file(&tmp_path);
+        return Err(err);
+    }
+    fs::rename(tmp_path, path)
+}
@@ -0,0 +1,170 @@
+use std::{
+    error::Error,
+    fs,
+    io::Read,
+    os::unix::fs::symlink,
+    path::PathBuf,
+    time::{Duration, SystemTime},
+};
+
+use base64::Engine;
+use log::{debug, warn};
+
+use crate::{helpers, utils::url};
+
+/// Refuse to cache anything bigger than this, album art is rarely more than a few MB
+const MAX_ART_SIZE: u64 = 20 * 1024 * 1024;
+
+/// Album art copied into the cache directory, so every kind of art URL ends up as a local file
+pub struct ArtCache {
+    dir: PathBuf,
+    max_size: u64,
+    agent: ureq::Agent,
+}
+
+impl ArtCache {
+    pub fn new(max_size: u64) -> Result<Self, std::io::Error> {
+        let dir = helpers::dir::get_and_create_dir(dirs::cache_dir)?.join("art");
+        fs::create_dir_all(&dir)?;
+
+        Ok(Self {
+            dir,
+            max_size,
+            agent: ureq::AgentBuilder::new()
+                .timeout(Duration::from_secs(10))
+                .build(),
+        })
+    }
+
+    /// Points at the art of the displayed player, for Waybar's image module
+    pub fn current_path(&self) -> PathBuf {
+        self.dir.join("current")
+    }
+
+    /// FNV-1a, which unlike the standard library's hasher is guaranteed to give
+    /// the same name for the same URL across versions, so the cache survives updates
+    fn file_name(art_url: &str) -> String {
+        let hash = art_url.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+        });
+        format!("{hash:016x}")
+    }
+
+    /// Get a local copy of the art, downloading or copying it if it isn't cached yet
+    pub fn resolve(&self, art_url: &str) -> Result<PathBuf, Box<dyn Error>> {
+        let path = self.dir.join(ArtCache::file_name(art_url));
+
+        if path.exists() {
+            // the modification time doubles as the last time it was used, for eviction
+            let _ = fs::File::options()
+                .write(true)
+                .open(&path)
+                .and_then(|file| file.set_modified(SystemTime::now()));
+            return Ok(path);
+        }
+
+        debug!("caching art from '{art_url}'");
+        let data = self.fetch(art_url)?;
+        helpers::file::write_atomically(&path, &data)?;
+        self.evict();
+
+        Ok(path)
+    }
+
+    fn fetch(&self, art_url: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+        if let Some(path) = url::file_path(art_url) {
+            if fs::metadata(&path)?.len() > MAX_ART_SIZE {
+                return Err("art is too large to cache".into());
+            }
+            return Ok(fs::read(path)?);
+        }
+
+        if let Some(data) = art_url.strip_prefix("data:") {
+            // data:[<mediatype>][;base64],<data>
+            let (media_type, data) = data.split_once(',').ok_or("invalid data URI")?;
+            let data = if media_type.ends_with(";base64") {
+                base64::engine::general_purpose::STANDARD.decode(data.trim())?
+            } else {
+                url::percent_decode_bytes(data)
+            };
+            if data.len() as u64 > MAX_ART_SIZE {
+                return Err("art is too large to cache".into());
+            }
+            return Ok(data);
+        }
+
+        if art_url.starts_with("http://") || art_url.starts_with("https://") {
+            let mut data = vec![];
+            self.agent
+                .get(art_url)
+                .call()?
+                .into_reader()
+                .take(MAX_ART_SIZE + 1)
+                .read_to_end(&mut data)?;
+            if data.len() as u64 > MAX_ART_SIZE {
+                return Err("art is too large to cache".into());
+            }
+            return Ok(data);
+        }
+
+        Err(format!("unsupported art URL '{art_url}'").into())
+    }
+
+    /// Point the `current` symlink at the given art, or remove it when there's none
+    pub fn set_current(&self, path: Option<&PathBuf>) -> Result<(), std::io::Error> {
+        let current = self.current_path();
+        let Some(path) = path else {
+            return match fs::remove_file(&current) {
+                Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err),
+                _ => Ok(()),
+            };
+        };
+
+        // swap the link in with a rename, so the image module never sees it missing
+        let tmp = helpers::file::tmp_path(&current);
+        symlink(path, &tmp)?;
+        fs::rename(&tmp, current).inspect_err(|_| {
+            let _ = fs::remove_file(&tmp);
+        })
+    }
+
+    /// Remove the least recently used art until the cache fits within its maximum size
+    fn evict(&self) {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(err) => {
+                warn!("failed to read the art cache: {err}");
+                return;
+            }
+        };
+
+        let mut files: Vec<(PathBuf, u64, SystemTime)> = entries
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let metadata = entry.metadata().ok()?;
+                // skips the `current` symlink and temporary files
+                if !metadata.is_file() || entry.path().extension().is_some() {
+                    return None;
+                }
+                Some((entry.path(), metadata.len(), metadata.modified().ok()?))
+            })
+            .collect();
+
+        let mut total: u64 = files.iter().map(|(_, size, _)| size).sum();
+        if total <= self.max_size {
+            return;
+        }
+
+        files.sort_by_key(|(_, _, modified)| *modified);
+        // the newest file is the one we just cached, so it's always kept
+        for (path, size, _) in files.iter().take(files.len().saturating_sub(1)) {
+            if total <= self.max_size {
+                break;
+            }
+            debug!("evicting {path:?} from the art cache");
+            if fs::remove_file(path).is_ok() {
+                total -= size;
+            }
+        }
+    }
+}
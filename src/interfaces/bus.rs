@@ -0,0 +1,83 @@
+use std::{
+    error::Error,
+    thread,
+    time::{Duration, Instant},
+};
+
+use dbus::{blocking::Connection, channel::Channel};
+use log::{info, warn};
+
+use crate::models::{args::Args, config::Config};
+
+/// The bus the players are on: the session bus, unless `--bus-address` or `[bus]` in config.toml
+/// point somewhere else, like a container's bus or an isolated one for testing
+#[derive(Debug, Clone, Default)]
+pub struct Bus {
+    address: Option<String>,
+}
+
+impl Bus {
+    pub fn new(args: &Args, config: Option<&Config>) -> Self {
+        Self {
+            address: args
+                .bus_address
+                .clone()
+                .or_else(|| config.and_then(|config| config.bus().address.clone())),
+        }
+    }
+
+    /// The session bus, whatever the players are on. Notifications always go here
+    pub fn session() -> Self {
+        Self::default()
+    }
+
+    pub fn is_session(&self) -> bool {
+        self.address.is_none()
+    }
+
+    pub fn connect(&self) -> Result<Connection, dbus::Error> {
+        match &self.address {
+            Some(address) => {
+                let mut channel = Channel::open_private(address)?;
+                channel.register()?;
+                Ok(Connection::from(channel))
+            }
+            None => Connection::new_session(),
+        }
+    }
+}
+
+/// How long to wait before connecting again, doubling after every failed attempt up to the max
+const MIN_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Runs `serve` again whenever it loses the bus, waiting longer after each failure, so a bus
+/// that restarts (e.g. a new login session) doesn't stop `service` for good. Returns once
+/// `serve` is done on its own
+pub fn supervise(service: &str, mut serve: impl FnMut() -> Result<(), Box<dyn Error>>) {
+    let mut backoff = MIN_BACKOFF;
+    loop {
+        let started = Instant::now();
+        let Err(err) = serve() else {
+            return;
+        };
+
+        // a connection that held up for a while is a fresh failure, not the same one again
+        if started.elapsed() > MAX_BACKOFF {
+            backoff = MIN_BACKOFF;
+        }
+        warn!("{service} can't reach the bus: {err}, trying again in {backoff:?}");
+        thread::sleep(backoff);
+        backoff = (backoff * 2).min(MAX_BACKOFF);
+        info!("{service} is reconnecting");
+    }
+}
+
+impl std::fmt::Display for Bus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.address {
+            Some(address) => write!(f, "{address}"),
+            None => write!(f, "the session bus"),
+        }
+    }
+}
@@ -0,0 +1,60 @@
+use std::{
+    error::Error,
+    fs,
+    io::{ErrorKind, Write},
+    os::unix::net::{UnixListener, UnixStream},
+    path::PathBuf,
+};
+
+use bincode::config;
+use log::{debug, warn};
+
+use crate::{helpers, models::control_command::ControlCommand};
+
+/// Every running instance binds its own socket in this directory,
+/// so commands reach all bars when several are running at once
+pub fn socket_dir() -> Result<PathBuf, std::io::Error> {
+    let directory =
+        helpers::dir::get_and_create_dir(|| dirs::runtime_dir().or_else(dirs::cache_dir))?
+            .join("control");
+
+    let _ = fs::create_dir(&directory);
+
+    Ok(directory)
+}
+
+pub fn bind() -> Result<UnixListener, std::io::Error> {
+    let path = socket_dir()?.join(format!("{}.sock", std::process::id()));
+
+    // a previous process with the same PID may have left its socket behind
+    let _ = fs::remove_file(&path);
+
+    UnixListener::bind(path)
+}
+
+/// Send a command to every running instance, returning how many received it
+pub fn send_command(command: &ControlCommand) -> Result<usize, Box<dyn Error>> {
+    let encoded = bincode::encode_to_vec(command, config::standard())?;
+
+    let mut reached = 0;
+    for entry in fs::read_dir(socket_dir()?)? {
+        let path = entry?.path();
+        if path.extension().is_none_or(|ext| ext != "sock") {
+            continue;
+        }
+
+        match UnixStream::connect(&path) {
+            Ok(mut stream) => {
+                stream.write_all(&encoded)?;
+                reached += 1;
+            }
+            Err(err) if err.kind() == ErrorKind::ConnectionRefused => {
+                debug!("removing stale control socket {path:?}");
+                let _ = fs::remove_file(&path);
+            }
+            Err(err) => warn!("failed to connect to control socket {path:?}: {err}"),
+        }
+    }
+
+    Ok(reached)
+}
@@ -0,0 +1,89 @@
+use std::{
+    error::Error,
+    fs,
+    io::{BufReader, ErrorKind, Read, Write},
+    os::unix::net::{UnixListener, UnixStream},
+    path::PathBuf,
+    time::Duration,
+};
+
+use bincode::{config, Decode, Encode};
+use log::debug;
+
+use crate::{event_bus::EventType, helpers};
+
+/// The events a bar needs to render, which the daemon forwards to its clients
+pub const FORWARDED_EVENTS: [EventType; 6] = [
+    EventType::PlayerStateChanged,
+    EventType::PlayerListChanged,
+    EventType::ListeningStatsChanged,
+    EventType::ArtCached,
+    EventType::LyricsLoaded,
+    EventType::BusStatusChanged,
+];
+
+/// An event published on the daemon's bus, as it is sent to clients
+#[derive(Debug, Encode, Decode)]
+pub struct Frame {
+    pub event_type: EventType,
+    pub data: Vec<u8>,
+}
+
+pub fn socket_path() -> Result<PathBuf, std::io::Error> {
+    Ok(
+        helpers::dir::get_and_create_dir(|| dirs::runtime_dir().or_else(dirs::cache_dir))?
+            .join("daemon.sock"),
+    )
+}
+
+/// Binds the daemon's socket, unless another daemon is already listening on it
+pub fn bind() -> Result<UnixListener, Box<dyn Error>> {
+    let path = socket_path()?;
+
+    match UnixStream::connect(&path) {
+        Ok(_) => return Err("a daemon is already running".into()),
+        Err(err) if err.kind() == ErrorKind::ConnectionRefused => {
+            debug!("removing stale daemon socket {path:?}");
+            let _ = fs::remove_file(&path);
+        }
+        Err(_) => (),
+    }
+
+    Ok(UnixListener::bind(path)?)
+}
+
+/// Greets a new client, so it can tell whether it understands our frames
+pub fn write_hello(stream: &mut UnixStream) -> Result<(), Box<dyn Error>> {
+    bincode::encode_into_std_write(env!("CARGO_PKG_VERSION"), stream, config::standard())?;
+    Ok(())
+}
+
+pub fn write_frame(stream: &mut UnixStream, frame: &Frame) -> Result<(), Box<dyn Error>> {
+    let encoded = bincode::encode_to_vec(frame, config::standard())?;
+    stream.write_all(&encoded)?;
+    Ok(())
+}
+
+pub fn read_frame(reader: &mut impl Read) -> Result<Frame, Box<dyn Error>> {
+    Ok(bincode::decode_from_std_read(reader, config::standard())?)
+}
+
+/// Connects to a running daemon of the same version, ready to read frames
+pub fn connect() -> Result<BufReader<UnixStream>, Box<dyn Error>> {
+    let stream = UnixStream::connect(socket_path()?)?;
+
+    // a daemon that never says hello is stuck, and we're better off without it
+    stream.set_read_timeout(Some(Duration::from_secs(2)))?;
+    let mut reader = BufReader::new(stream);
+    let version: String = bincode::decode_from_std_read(&mut reader, config::standard())?;
+    if version != env!("CARGO_PKG_VERSION") {
+        return Err(format!(
+            "the daemon runs version {version}, but this is {}",
+            env!("CARGO_PKG_VERSION")
+        )
+        .into());
+    }
+    reader.get_ref().set_read_timeout(None)?;
+
+    Ok(reader)
+}
@@ -1,33 +1,84 @@
 use std::error::Error;
+use std::sync::Mutex;
 use std::time::Duration;
 
 use dbus::{
-    arg::PropMap,
+    arg::{PropMap, RefArg, Variant},
     blocking::{stdintf::org_freedesktop_dbus::Properties, Connection, Proxy},
 };
-
-use crate::models::{
-    mpris_metadata::MprisMetadata, mpris_playback::MprisPlayback, playback_state::PlaybackState,
+use log::warn;
+
+use crate::{
+    interfaces::bus::Bus,
+    models::{
+        mpris_metadata::MprisMetadata, mpris_playback::MprisPlayback,
+        playback_state::PlaybackState, player_client::PlayerClient,
+    },
 };
 
 pub struct DBusClient {
-    conn: Connection,
+    /// Connected on first use, and again whenever the bus went away since
+    conn: Mutex<Option<Connection>>,
+    bus: Bus,
 }
 
 impl DBusClient {
-    pub fn new() -> Self {
+    /// A client that connects once it's needed, for services that outlive the bus
+    pub fn new(bus: &Bus) -> Self {
+        let conn = bus
+            .connect()
+            .inspect_err(|err| warn!("failed to connect to {bus}, trying again later: {err}"))
+            .ok();
+
         Self {
-            conn: Connection::new_session().expect("failed to create DBus connection"),
+            conn: Mutex::new(conn),
+            bus: bus.clone(),
         }
     }
 
-    fn get_players(&self) -> Result<Vec<String>, Box<dyn std::error::Error>> {
-        let proxy = self
+    /// A client that is connected already, for commands that can't do anything without the bus
+    pub fn try_new(bus: &Bus) -> Result<Self, dbus::Error> {
+        Ok(Self {
+            conn: Mutex::new(Some(bus.connect()?)),
+            bus: bus.clone(),
+        })
+    }
+
+    /// Runs `f` on our connection, connecting again first if the bus went away
+    fn with_conn<T, E: From<dbus::Error>>(
+        &self,
+        f: impl FnOnce(&Connection) -> Result<T, E>,
+    ) -> Result<T, E> {
+        let mut conn = self
             .conn
-            .with_proxy("org.freedesktop.DBus", "/", Duration::from_millis(5000));
+            .lock()
+            .map_err(|_| dbus::Error::new_failed("the D-Bus connection is poisoned"))?;
+
+        let connected = match conn.take().filter(|c| c.channel().is_connected()) {
+            Some(connected) => connected,
+            None => self.bus.connect()?,
+        };
+        f(conn.insert(connected))
+    }
 
-        let (names,): (Vec<String>,) =
-            proxy.method_call("org.freedesktop.DBus", "ListNames", ())?;
+    /// Drops the connection, so the next call connects again, e.g. once the bus is back
+    pub fn reconnect(&self) {
+        if let Ok(mut conn) = self.conn.lock() {
+            *conn = None;
+        }
+    }
+
+    /// The bus this client is connected to, for services that need a connection of their own
+    pub fn bus(&self) -> &Bus {
+        &self.bus
+    }
+
+    /// Bus names of every MPRIS player, like "org.mpris.MediaPlayer2.spotify"
+    pub fn get_players(&self) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        let (names,): (Vec<String>,) = self.with_conn(|conn| {
+            conn.with_proxy("org.freedesktop.DBus", "/", Duration::from_millis(5000))
+                .method_call("org.freedesktop.DBus", "ListNames", ())
+        })?;
 
         let players: Vec<String> = names
             .iter()
@@ -39,12 +90,10 @@ impl DBusClient {
     }
 
     pub fn query_playback_status(&self, player_id: &str) -> Result<MprisPlayback, dbus::Error> {
-        let proxy = self.conn.with_proxy(
-            player_id,
-            "/org/mpris/MediaPlayer2",
-            Duration::from_millis(5000),
-        );
-        let result: String = proxy.get("org.mpris.MediaPlayer2.Player", "PlaybackStatus")?;
+        let result: String = self.with_conn(|conn| {
+            DBusClient::media_player_proxy(conn, player_id)
+                .get("org.mpris.MediaPlayer2.Player", "PlaybackStatus")
+        })?;
         Ok(MprisPlayback::new_with_playing(
             player_id.to_string(),
             PlaybackState::from_string(&result),
@@ -52,12 +101,10 @@ impl DBusClient {
     }
 
     pub fn query_metadata(&self, player_id: &str) -> Result<MprisMetadata, Box<dyn Error>> {
-        let proxy = self.conn.with_proxy(
-            player_id,
-            "/org/mpris/MediaPlayer2",
-            Duration::from_millis(5000),
-        );
-        let result: PropMap = proxy.get("org.mpris.MediaPlayer2.Player", "Metadata")?;
+        let result: PropMap = self.with_conn(|conn| {
+            DBusClient::media_player_proxy(conn, player_id)
+                .get("org.mpris.MediaPlayer2.Player", "Metadata")
+        })?;
 
         Ok(MprisMetadata::from_dbus_propmap(
             player_id.to_string(),
@@ -65,57 +112,125 @@ impl DBusClient {
         ))
     }
 
-    pub fn query_mediaplayer_identity(&self, player_id: &str) -> Result<String, Box<dyn Error>> {
-        let proxy = self.get_media_player_proxy(player_id);
-        let identity: String = proxy.get("org.mpris.MediaPlayer2", "Identity")?;
-
-        Ok(identity)
+    /// Every property of one of the player's interfaces, like "org.mpris.MediaPlayer2"
+    pub fn query_properties(
+        &self,
+        player_id: &str,
+        interface: &str,
+    ) -> Result<PropMap, dbus::Error> {
+        self.with_conn(|conn| DBusClient::media_player_proxy(conn, player_id).get_all(interface))
     }
 
-    pub fn get_media_player_proxy<'a>(&'a self, player_id: &'a str) -> Proxy<'a, &'a Connection> {
-        self.conn.with_proxy(
-            player_id,
-            "/org/mpris/MediaPlayer2",
-            Duration::from_millis(5000),
-        )
+    /// Playback position in microseconds
+    pub fn query_position(&self, player_id: &str) -> Result<i64, dbus::Error> {
+        self.with_conn(|conn| {
+            DBusClient::media_player_proxy(conn, player_id)
+                .get("org.mpris.MediaPlayer2.Player", "Position")
+        })
     }
 
-    fn call_player_method(
-        &self,
-        player_id: &str,
-        method: &str,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        let proxy = self.get_media_player_proxy(player_id);
-        proxy.method_call::<(), _, _, _>("org.mpris.MediaPlayer2.Player", method, ())?;
-        Ok(())
+    /// Everything we need to know about a player we haven't heard from yet
+    pub fn query_player(&self, player_id: &str) -> Result<PlayerClient, Box<dyn Error>> {
+        let mut player = PlayerClient::new(
+            self.query_mediaplayer_identity(player_id)?,
+            self.query_metadata(player_id)?,
+        );
+        player.update_playback_state(self.query_playback_status(player_id)?);
+
+        // not every player reports its position
+        if let Ok(position) = self.query_position(player_id) {
+            player.update_position(position.max(0) as u128);
+        }
+
+        Ok(player)
     }
 
-    pub fn play_mpris_player(&self, player_id: &str) -> Result<(), Box<dyn std::error::Error>> {
-        self.call_player_method(player_id, "Play")?;
-        Ok(())
+    pub fn query_mediaplayer_identity(&self, player_id: &str) -> Result<String, Box<dyn Error>> {
+        let identity: String = self.with_conn(|conn| {
+            DBusClient::media_player_proxy(conn, player_id)
+                .get("org.mpris.MediaPlayer2", "Identity")
+        })?;
+
+        Ok(identity)
     }
 
-    pub fn pause_mpris_player(&self, player_id: &str) -> Result<(), Box<dyn std::error::Error>> {
-        self.call_player_method(player_id, "Pause")?;
-        Ok(())
+    /// The unique name of the connection owning `name`, like ":1.42"
+    pub fn query_name_owner(&self, name: &str) -> Result<String, dbus::Error> {
+        let (owner,): (String,) = self.with_conn(|conn| {
+            conn.with_proxy(
+                "org.freedesktop.DBus",
+                "/org/freedesktop/DBus",
+                Duration::from_millis(5000),
+            )
+            .method_call("org.freedesktop.DBus", "GetNameOwner", (name,))
+        })?;
+        Ok(owner)
     }
 
-    pub fn play_pause_mpris_player(
-        &self,
-        player_id: &str,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        self.call_player_method(player_id, "PlayPause")?;
-        Ok(())
+    /// Calls a method of the player's `org.mpris.MediaPlayer2.Player` interface, like "Next"
+    pub fn call_player_method(&self, player_id: &str, method: &str) -> Result<(), dbus::Error> {
+        self.with_conn(|conn| {
+            DBusClient::media_player_proxy(conn, player_id).method_call(
+                "org.mpris.MediaPlayer2.Player",
+                method,
+                (),
+            )
+        })
     }
 
-    pub fn next_mpris_player(&self, player_id: &str) -> Result<(), Box<dyn std::error::Error>> {
-        self.call_player_method(player_id, "Next")?;
-        Ok(())
+    fn media_player_proxy<'a>(
+        conn: &'a Connection,
+        player_id: &'a str,
+    ) -> Proxy<'a, &'a Connection> {
+        conn.with_proxy(
+            player_id,
+            "/org/mpris/MediaPlayer2",
+            Duration::from_millis(5000),
+        )
     }
 
-    pub fn previous_mpris_player(&self, player_id: &str) -> Result<(), Box<dyn std::error::Error>> {
-        self.call_player_method(player_id, "Previous")?;
-        Ok(())
+    /// Send a desktop notification, replacing the notification with ID `replaces_id` if it's still shown.
+    /// Returns the ID of the new notification
+    pub fn notify(
+        &self,
+        replaces_id: u32,
+        summary: &str,
+        body: &str,
+        image_path: Option<&str>,
+        expire_timeout_ms: i32,
+    ) -> Result<u32, Box<dyn std::error::Error>> {
+        let mut hints = PropMap::new();
+        if let Some(image_path) = image_path {
+            hints.insert(
+                String::from("image-path"),
+                Variant(Box::new(image_path.to_string()) as Box<dyn RefArg>),
+            );
+        }
+
+        let actions: Vec<&str> = vec![];
+        let (id,): (u32,) = self.with_conn(|conn| {
+            conn.with_proxy(
+                "org.freedesktop.Notifications",
+                "/org/freedesktop/Notifications",
+                Duration::from_millis(5000),
+            )
+            .method_call(
+                "org.freedesktop.Notifications",
+                "Notify",
+                (
+                    "waybar-module-music",
+                    replaces_id,
+                    "",
+                    summary,
+                    body,
+                    actions,
+                    hints,
+                    expire_timeout_ms,
+                ),
+            )
+        })?;
+
+        Ok(id)
     }
 }
 
@@ -0,0 +1,125 @@
+use std::time::Duration;
+
+use serde_json::{json, Value};
+
+use crate::models::{play::Play, player_state::PlayerState};
+
+pub enum SubmitError {
+    /// The server refused the listens, sending them again won't help
+    Rejected(String),
+    /// The server refused our token, nothing will get through until the config is fixed
+    Unauthorized,
+    /// The server couldn't be reached or had a problem of its own, worth trying again later
+    Unreachable(String),
+}
+
+impl std::fmt::Display for SubmitError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SubmitError::Rejected(msg) => write!(f, "listens were rejected: {msg}"),
+            SubmitError::Unauthorized => {
+                write!(f, "the token was refused, check [scrobbler] in config.toml")
+            }
+            SubmitError::Unreachable(msg) => write!(f, "failed to reach the server: {msg}"),
+        }
+    }
+}
+
+/// Client for the ListenBrainz submission API, which Maloja and other self-hosted servers also speak
+pub struct ListenBrainzClient {
+    endpoint: String,
+    token: String,
+    agent: ureq::Agent,
+}
+
+impl ListenBrainzClient {
+    pub fn new(endpoint: &str, token: &str) -> Self {
+        Self {
+            endpoint: endpoint.trim_end_matches('/').to_string(),
+            token: token.to_string(),
+            agent: ureq::AgentBuilder::new()
+                .timeout(Duration::from_secs(10))
+                .build(),
+        }
+    }
+
+    fn track_metadata(artist: &str, title: &str, album: &str, player: &str, length: u64) -> Value {
+        let mut additional_info = json!({
+            "media_player": player,
+            "submission_client": env!("CARGO_PKG_NAME"),
+            "submission_client_version": env!("CARGO_PKG_VERSION"),
+        });
+        if length > 0 {
+            additional_info["duration_ms"] = json!(length * 1000);
+        }
+
+        let mut metadata = json!({
+            "artist_name": artist,
+            "track_name": title,
+            "additional_info": additional_info,
+        });
+        if !album.is_empty() {
+            metadata["release_name"] = json!(album);
+        }
+        metadata
+    }
+
+    fn submit(&self, body: Value) -> Result<(), SubmitError> {
+        match self
+            .agent
+            .post(&format!("{}/1/submit-listens", self.endpoint))
+            .set("Authorization", &format!("Token {}", self.token))
+            .send_json(body)
+        {
+            Ok(_) => Ok(()),
+            // the listens themselves are fine, so they're kept around until this is sorted out
+            Err(ureq::Error::Status(401 | 403, _)) => Err(SubmitError::Unauthorized),
+            Err(ureq::Error::Status(429, _)) => {
+                Err(SubmitError::Unreachable(String::from("rate limited")))
+            }
+            Err(ureq::Error::Status(code, response)) if code < 500 => Err(SubmitError::Rejected(
+                format!("{code}: {}", response.into_string().unwrap_or_default()),
+            )),
+            Err(err) => Err(SubmitError::Unreachable(err.to_string())),
+        }
+    }
+
+    /// Submit finished plays in a single request, keep batches well below the server's limit of 1000
+    pub fn submit_listens(&self, plays: &[Play]) -> Result<(), SubmitError> {
+        let payload: Vec<Value> = plays
+            .iter()
+            .map(|play| {
+                json!({
+                    "listened_at": play.timestamp,
+                    "track_metadata": ListenBrainzClient::track_metadata(
+                        &play.artist,
+                        &play.title,
+                        &play.album,
+                        &play.player,
+                        play.length,
+                    ),
+                })
+            })
+            .collect();
+
+        self.submit(json!({
+            "listen_type": if payload.len() == 1 { "single" } else { "import" },
+            "payload": payload,
+        }))
+    }
+
+    pub fn playing_now(&self, state: &PlayerState) -> Result<(), SubmitError> {
+        self.submit(json!({
+            "listen_type": "playing_now",
+            "payload": [{
+                "track_metadata": ListenBrainzClient::track_metadata(
+                    &state.artist,
+                    &state.title,
+                    &state.album,
+                    &state.player_name,
+                    state.length / 1000 / 1000,
+                ),
+            }],
+        }))
+    }
+}
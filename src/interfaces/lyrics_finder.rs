@@ -0,0 +1,53 @@
+use std::{fs, path::PathBuf};
+
+use log::debug;
+
+use crate::{
+    models::{lyrics::Lyrics, player_state::PlayerState},
+    utils::url,
+};
+
+/// Looks for `.lrc` files next to the playing file, or named `Artist - Title.lrc` in the configured directories
+pub struct LyricsFinder {
+    directories: Vec<PathBuf>,
+}
+
+impl LyricsFinder {
+    pub fn new(directories: &[String]) -> Self {
+        Self {
+            directories: directories
+                .iter()
+                .map(|dir| match (dir.strip_prefix("~/"), dirs::home_dir()) {
+                    (Some(rest), Some(home)) => home.join(rest),
+                    _ => PathBuf::from(dir),
+                })
+                .collect(),
+        }
+    }
+
+    fn candidates(&self, state: &PlayerState) -> Vec<PathBuf> {
+        let mut candidates = vec![];
+
+        if let Some(path) = url::file_path(&state.url) {
+            candidates.push(path.with_extension("lrc"));
+        }
+
+        if !state.artist.is_empty() && !state.title.is_empty() {
+            // slashes would point into another directory
+            let file_name = format!("{} - {}.lrc", state.artist, state.title).replace('/', "_");
+            candidates.extend(self.directories.iter().map(|dir| dir.join(&file_name)));
+        }
+
+        candidates
+    }
+
+    pub fn find(&self, state: &PlayerState) -> Option<Lyrics> {
+        self.candidates(state).into_iter().find_map(|path| {
+            let data = fs::read(&path).ok()?;
+            debug!("found lyrics at {path:?}");
+            // LRC files in the wild aren't always UTF-8, so do our best instead of giving up
+            let lyrics = Lyrics::parse(&String::from_utf8_lossy(&data));
+            (!lyrics.is_empty()).then_some(lyrics)
+        })
+    }
+}
@@ -1 +1,10 @@
+pub mod art_cache;
+pub mod bus;
+pub mod control_socket;
+pub mod daemon_socket;
 pub mod dbus_client;
+pub mod listenbrainz_client;
+pub mod lyrics_finder;
+pub mod play_store;
+pub mod player_queries;
+pub mod recording;
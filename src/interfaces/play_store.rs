@@ -0,0 +1,83 @@
+use std::{
+    error::Error,
+    fs::{self, OpenOptions},
+    io::{BufRead, BufReader, ErrorKind, Write},
+    path::PathBuf,
+};
+
+use log::warn;
+
+use crate::{helpers, models::play::Play};
+
+/// A list of plays, stored as JSON Lines with one play per line,
+/// like the listening history or the listens waiting to be scrobbled
+pub struct PlayStore {
+    path: PathBuf,
+}
+
+impl PlayStore {
+    /// The listening history
+    pub fn history() -> Result<Self, std::io::Error> {
+        PlayStore::open("history.jsonl")
+    }
+
+    /// A list of plays in the data directory
+    pub fn open(file_name: &str) -> Result<Self, std::io::Error> {
+        let data_dir = helpers::dir::get_and_create_dir(dirs::data_dir)?;
+        Ok(Self {
+            path: data_dir.join(file_name),
+        })
+    }
+
+    #[cfg(test)]
+    pub fn at(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    pub fn path(&self) -> &PathBuf {
+        &self.path
+    }
+
+    pub fn append(&self, play: &Play) -> Result<(), Box<dyn Error>> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{}", serde_json::to_string(play)?)?;
+        Ok(())
+    }
+
+    pub fn replace_all(&self, plays: &[Play]) -> Result<(), Box<dyn Error>> {
+        let mut contents = String::new();
+        for play in plays {
+            contents.push_str(&serde_json::to_string(play)?);
+            contents.push('\n');
+        }
+        helpers::file::write_atomically(&self.path, contents.as_bytes())?;
+        Ok(())
+    }
+
+    /// Every recorded play, oldest first
+    pub fn read_all(&self) -> Result<Vec<Play>, Box<dyn Error>> {
+        let file = match fs::File::open(&self.path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(vec![]),
+            Err(err) => return Err(err.into()),
+        };
+
+        let mut plays = vec![];
+        for (i, line) in BufReader::new(file).lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            match serde_json::from_str(&line) {
+                Ok(play) => plays.push(play),
+                Err(err) => warn!("skipping invalid history entry on line {}: {err}", i + 1),
+            }
+        }
+
+        Ok(plays)
+    }
+}
@@ -0,0 +1,233 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    error::Error,
+    sync::{Arc, Mutex},
+};
+
+use bincode::config;
+use log::debug;
+
+use crate::{
+    interfaces::{
+        dbus_client::DBusClient,
+        recording::{Entry, Record, Recording},
+    },
+    models::{mpris_metadata::MprisMetadata, mpris_playback::MprisPlayback},
+    services::mpris_proxy,
+};
+
+/// What PlayerManager asks the players, so `--record` can write the answers down
+/// and `--replay` can give the same answers again without D-Bus
+pub trait PlayerQueries: Send + Sync {
+    /// Unique names of every MPRIS player on the bus, like ":1.42", except our own proxy
+    fn list_players(&self) -> Result<Vec<String>, Box<dyn Error>>;
+
+    fn query_mediaplayer_identity(&self, player_id: &str) -> Result<String, Box<dyn Error>>;
+
+    fn query_playback_status(&self, player_id: &str) -> Result<MprisPlayback, Box<dyn Error>>;
+
+    fn query_metadata(&self, player_id: &str) -> Result<MprisMetadata, Box<dyn Error>>;
+
+    /// Playback position in microseconds
+    fn query_position(&self, player_id: &str) -> Result<i64, Box<dyn Error>>;
+
+    /// Calls a method of the player's `org.mpris.MediaPlayer2.Player` interface, like "Next"
+    fn call_player_method(&self, player_id: &str, method: &str) -> Result<(), Box<dyn Error>>;
+
+    fn play_mpris_player(&self, player_id: &str) -> Result<(), Box<dyn Error>> {
+        self.call_player_method(player_id, "Play")
+    }
+
+    fn pause_mpris_player(&self, player_id: &str) -> Result<(), Box<dyn Error>> {
+        self.call_player_method(player_id, "Pause")
+    }
+
+    fn play_pause_mpris_player(&self, player_id: &str) -> Result<(), Box<dyn Error>> {
+        self.call_player_method(player_id, "PlayPause")
+    }
+
+    fn next_mpris_player(&self, player_id: &str) -> Result<(), Box<dyn Error>> {
+        self.call_player_method(player_id, "Next")
+    }
+
+    fn previous_mpris_player(&self, player_id: &str) -> Result<(), Box<dyn Error>> {
+        self.call_player_method(player_id, "Previous")
+    }
+}
+
+impl PlayerQueries for DBusClient {
+    fn list_players(&self) -> Result<Vec<String>, Box<dyn Error>> {
+        Ok(self
+            .get_players()?
+            .iter()
+            .filter(|name| name.as_str() != mpris_proxy::BUS_NAME)
+            .filter_map(|name| self.query_name_owner(name).ok())
+            .collect())
+    }
+
+    fn query_mediaplayer_identity(&self, player_id: &str) -> Result<String, Box<dyn Error>> {
+        DBusClient::query_mediaplayer_identity(self, player_id)
+    }
+
+    fn query_playback_status(&self, player_id: &str) -> Result<MprisPlayback, Box<dyn Error>> {
+        Ok(DBusClient::query_playback_status(self, player_id)?)
+    }
+
+    fn query_metadata(&self, player_id: &str) -> Result<MprisMetadata, Box<dyn Error>> {
+        DBusClient::query_metadata(self, player_id)
+    }
+
+    fn query_position(&self, player_id: &str) -> Result<i64, Box<dyn Error>> {
+        Ok(DBusClient::query_position(self, player_id)?)
+    }
+
+    fn call_player_method(&self, player_id: &str, method: &str) -> Result<(), Box<dyn Error>> {
+        Ok(DBusClient::call_player_method(self, player_id, method)?)
+    }
+}
+
+/// Asks the players over D-Bus, and writes every answer to the `--record` file
+pub struct RecordingQueries {
+    dbus_client: Arc<DBusClient>,
+    recording: Arc<Recording>,
+}
+
+impl RecordingQueries {
+    pub fn new(dbus_client: Arc<DBusClient>, recording: Arc<Recording>) -> Self {
+        Self {
+            dbus_client,
+            recording,
+        }
+    }
+
+    fn record<T: bincode::Encode>(
+        &self,
+        query: &str,
+        player_id: &str,
+        answer: Result<T, Box<dyn Error>>,
+    ) -> Result<T, Box<dyn Error>> {
+        let recorded = match &answer {
+            Ok(value) => {
+                bincode::encode_to_vec(value, config::standard()).map_err(|err| err.to_string())
+            }
+            Err(err) => Err(err.to_string()),
+        };
+        self.recording.write(Entry::Query {
+            query: query.to_string(),
+            player_id: player_id.to_string(),
+            answer: recorded,
+        });
+        answer
+    }
+}
+
+impl PlayerQueries for RecordingQueries {
+    fn list_players(&self) -> Result<Vec<String>, Box<dyn Error>> {
+        let answer = PlayerQueries::list_players(self.dbus_client.as_ref());
+        self.record("players", "", answer)
+    }
+
+    fn query_mediaplayer_identity(&self, player_id: &str) -> Result<String, Box<dyn Error>> {
+        let answer = DBusClient::query_mediaplayer_identity(&self.dbus_client, player_id);
+        self.record("identity", player_id, answer)
+    }
+
+    fn query_playback_status(&self, player_id: &str) -> Result<MprisPlayback, Box<dyn Error>> {
+        let answer = DBusClient::query_playback_status(&self.dbus_client, player_id)
+            .map_err(|err| err.into());
+        self.record("playback_status", player_id, answer)
+    }
+
+    fn query_metadata(&self, player_id: &str) -> Result<MprisMetadata, Box<dyn Error>> {
+        let answer = DBusClient::query_metadata(&self.dbus_client, player_id);
+        self.record("metadata", player_id, answer)
+    }
+
+    fn query_position(&self, player_id: &str) -> Result<i64, Box<dyn Error>> {
+        let answer =
+            DBusClient::query_position(&self.dbus_client, player_id).map_err(|err| err.into());
+        self.record("position", player_id, answer)
+    }
+
+    fn call_player_method(&self, player_id: &str, method: &str) -> Result<(), Box<dyn Error>> {
+        PlayerQueries::call_player_method(self.dbus_client.as_ref(), player_id, method)
+    }
+}
+
+type Answers = VecDeque<Result<Vec<u8>, String>>;
+
+/// Gives the answers of a `--record`ed session again, in the order they were given for each
+/// query and player
+pub struct ReplayedQueries {
+    answers: Mutex<HashMap<(String, String), Answers>>,
+}
+
+impl ReplayedQueries {
+    pub fn new(records: &[Record]) -> Self {
+        let mut answers: HashMap<(String, String), Answers> = HashMap::new();
+        for record in records {
+            if let Entry::Query {
+                query,
+                player_id,
+                answer,
+            } = &record.entry
+            {
+                answers
+                    .entry((query.clone(), player_id.clone()))
+                    .or_default()
+                    .push_back(answer.clone());
+            }
+        }
+
+        Self {
+            answers: Mutex::new(answers),
+        }
+    }
+
+    fn answer<T: bincode::Decode<()>>(
+        &self,
+        query: &str,
+        player_id: &str,
+    ) -> Result<T, Box<dyn Error>> {
+        let answer = self
+            .answers
+            .lock()
+            .map_err(|_| "replayed answers are poisoned")?
+            .get_mut(&(query.to_string(), player_id.to_string()))
+            .and_then(|answers| answers.pop_front())
+            .ok_or_else(|| {
+                format!("the recording has no more '{query}' answers for '{player_id}'")
+            })?;
+
+        let encoded = answer?;
+        Ok(bincode::decode_from_slice(&encoded[..], config::standard())?.0)
+    }
+}
+
+impl PlayerQueries for ReplayedQueries {
+    fn list_players(&self) -> Result<Vec<String>, Box<dyn Error>> {
+        self.answer("players", "")
+    }
+
+    fn query_mediaplayer_identity(&self, player_id: &str) -> Result<String, Box<dyn Error>> {
+        self.answer("identity", player_id)
+    }
+
+    fn query_playback_status(&self, player_id: &str) -> Result<MprisPlayback, Box<dyn Error>> {
+        self.answer("playback_status", player_id)
+    }
+
+    fn query_metadata(&self, player_id: &str) -> Result<MprisMetadata, Box<dyn Error>> {
+        self.answer("metadata", player_id)
+    }
+
+    fn query_position(&self, player_id: &str) -> Result<i64, Box<dyn Error>> {
+        self.answer("position", player_id)
+    }
+
+    fn call_player_method(&self, player_id: &str, method: &str) -> Result<(), Box<dyn Error>> {
+        // the player's reaction is in the recording already, if it was recorded
+        debug!("not calling '{method}' on '{player_id}' while replaying");
+        Ok(())
+    }
+}
@@ -0,0 +1,99 @@
+use std::{
+    error::Error,
+    fs::File,
+    io::{BufReader, BufWriter, ErrorKind, Write},
+    path::Path,
+    sync::Mutex,
+    time::Instant,
+};
+
+use bincode::{config, error::DecodeError, Decode, Encode};
+use log::warn;
+
+use crate::event_bus::EventType;
+
+/// Something that happened while recording with `--record`
+#[derive(Debug, Encode, Decode)]
+pub enum Entry {
+    /// An event DBusMonitor or a control command published
+    Event {
+        event_type: EventType,
+        data: Vec<u8>,
+    },
+    /// PlayerManager asking a player, and what it got back
+    Query {
+        query: String,
+        player_id: String,
+        answer: Result<Vec<u8>, String>,
+    },
+}
+
+#[derive(Debug, Encode, Decode)]
+pub struct Record {
+    /// Milliseconds since the recording started
+    pub at_ms: u64,
+    pub entry: Entry,
+}
+
+/// The `--record` file, which starts with the version that wrote it followed by `Record`s
+pub struct Recording {
+    writer: Mutex<BufWriter<File>>,
+    started: Instant,
+}
+
+impl Recording {
+    pub fn create(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        bincode::encode_into_std_write(env!("CARGO_PKG_VERSION"), &mut writer, config::standard())?;
+        writer.flush()?;
+
+        Ok(Self {
+            writer: Mutex::new(writer),
+            started: Instant::now(),
+        })
+    }
+
+    pub fn write(&self, entry: Entry) {
+        let record = Record {
+            at_ms: self.started.elapsed().as_millis() as u64,
+            entry,
+        };
+
+        let Ok(mut writer) = self.writer.lock() else {
+            return;
+        };
+        // flushed right away, as the module is usually stopped by killing it
+        let written = bincode::encode_into_std_write(&record, &mut *writer, config::standard())
+            .map_err(|err| err.to_string())
+            .and_then(|_| writer.flush().map_err(|err| err.to_string()));
+        if let Err(err) = written {
+            warn!("failed to write to the recording: {err}");
+        }
+    }
+
+    /// Every record in a file written by the same version
+    pub fn read(path: &Path) -> Result<Vec<Record>, Box<dyn Error>> {
+        let mut reader = BufReader::new(File::open(path)?);
+        let version: String = bincode::decode_from_std_read(&mut reader, config::standard())?;
+        if version != env!("CARGO_PKG_VERSION") {
+            return Err(format!(
+                "{path:?} was recorded by version {version}, but this is {}",
+                env!("CARGO_PKG_VERSION")
+            )
+            .into());
+        }
+
+        let mut records = vec![];
+        loop {
+            match bincode::decode_from_std_read(&mut reader, config::standard()) {
+                Ok(record) => records.push(record),
+                Err(DecodeError::Io { inner, .. }) if inner.kind() == ErrorKind::UnexpectedEof => {
+                    break
+                }
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok(records)
+    }
+}
@@ -1,26 +1,43 @@
-use std::{fs::File, sync::Arc, thread};
+use std::{fs::File, path::Path, sync::Arc, thread};
 
 use clap::Parser;
-use event_bus::EventBus;
-use interfaces::dbus_client::DBusClient;
-use log::info;
-use models::{args::Args, config::Config};
+use event_bus::{EventBus, EventBusHandle};
+use interfaces::{
+    bus::Bus,
+    daemon_socket,
+    dbus_client::DBusClient,
+    player_queries::{PlayerQueries, RecordingQueries, ReplayedQueries},
+    recording::Recording,
+};
+use log::{info, warn};
+use models::{
+    args::{Args, OutputMode},
+    config::Config,
+};
 use services::{
-    dbus_monitor::DBusMonitor, display::Display, player_manager::PlayerManager, runnable::Runnable,
+    art_fetcher::ArtFetcher, click_listener::ClickListener, control_listener::ControlListener,
+    daemon_client::DaemonClient, daemon_server::DaemonServer, dbus_monitor::DBusMonitor,
+    dbus_service::DBusService, display::Display, event_recorder::EventRecorder,
+    history_recorder::HistoryRecorder, hook_runner::HookRunner, lyrics_loader::LyricsLoader,
+    mpris_proxy::MprisProxy, notifier::Notifier, player_manager::PlayerManager, runnable::Runnable,
+    scrobbler::Scrobbler, single_owner::SingleOwner, state_exporter::StateExporter,
 };
 use simplelog::{CombinedLogger, Config as LogConfig, WriteLogger};
 
+mod commands;
 mod effects;
 mod event_bus;
 mod helpers;
 mod interfaces;
 mod models;
+mod outputs;
+mod selection;
 mod services;
 mod utils;
 
-fn init_logger(debug: bool) -> Result<(), Box<dyn std::error::Error>> {
+fn init_logger(debug: bool, file_name: &str) -> Result<(), Box<dyn std::error::Error>> {
     let cache_dir = helpers::dir::get_and_create_dir(dirs::cache_dir)?;
-    let log_path = cache_dir.join("app.log");
+    let log_path = cache_dir.join(file_name);
 
     CombinedLogger::init(vec![WriteLogger::new(
         if debug {
@@ -34,11 +51,186 @@ fn init_logger(debug: bool) -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+/// Everything that watches the players and acts on them, which is what the daemon shares between bars
+fn backend_services(
+    args: &Arc<Args>,
+    config: &Arc<Config>,
+    event_bus_handle: &EventBusHandle,
+    recording: Option<&Arc<Recording>>,
+) -> Vec<Arc<dyn Runnable>> {
+    let bus = Bus::new(args, Some(config));
+    let dbus_client = Arc::new(DBusClient::new(&bus));
+    let queries: Arc<dyn PlayerQueries> = match recording {
+        Some(recording) => Arc::new(RecordingQueries::new(
+            dbus_client.clone(),
+            recording.clone(),
+        )),
+        None => dbus_client.clone(),
+    };
+
+    let mut services: Vec<Arc<dyn Runnable>> = vec![
+        Arc::new(DBusMonitor::new(
+            args.clone(),
+            event_bus_handle.clone(),
+            dbus_client.clone(),
+            config.mpris_proxy().enabled,
+        )),
+        Arc::new(PlayerManager::new(
+            args.clone(),
+            event_bus_handle.clone(),
+            queries,
+        )),
+        Arc::new(ControlListener::new(event_bus_handle.clone())),
+    ];
+
+    // run by one instance at a time, see SingleOwner
+    let mut play_services: Vec<Arc<dyn Runnable + Send + Sync>> = vec![];
+
+    if !config.hooks().is_empty() {
+        play_services.push(Arc::new(HookRunner::new(
+            config.clone(),
+            event_bus_handle.clone(),
+        )));
+    }
+
+    if config.notifications().enabled {
+        // the notification server is on the session bus, even when the players are elsewhere
+        let session_client = if bus.is_session() {
+            dbus_client.clone()
+        } else {
+            Arc::new(DBusClient::new(&Bus::session()))
+        };
+        play_services.push(Arc::new(Notifier::new(
+            config.clone(),
+            event_bus_handle.clone(),
+            session_client,
+        )));
+    }
+
+    if config.history().enabled {
+        play_services.push(Arc::new(HistoryRecorder::new(
+            config.clone(),
+            event_bus_handle.clone(),
+        )));
+    }
+
+    if config.scrobbler().enabled {
+        play_services.push(Arc::new(Scrobbler::new(
+            config.clone(),
+            event_bus_handle.clone(),
+        )));
+    }
+
+    // every instance would write the same file
+    if config.now_playing().enabled {
+        play_services.push(Arc::new(StateExporter::new(
+            config.clone(),
+            event_bus_handle.clone(),
+        )));
+    }
+
+    if !play_services.is_empty() {
+        services.push(Arc::new(SingleOwner::new("plays.lock", play_services)));
+    }
+
+    if config.art_cache().enabled {
+        services.push(Arc::new(ArtFetcher::new(
+            config.clone(),
+            event_bus_handle.clone(),
+        )));
+    }
+
+    if config.lyrics().enabled {
+        services.push(Arc::new(LyricsLoader::new(
+            config.clone(),
+            event_bus_handle.clone(),
+        )));
+    }
+
+    if config.dbus_service().enabled {
+        // scripts and bar click handlers look for us on the session bus, wherever the players are
+        services.push(Arc::new(DBusService::new(
+            event_bus_handle.clone(),
+            Bus::session(),
+        )));
+    }
+
+    if config.mpris_proxy().enabled {
+        services.push(Arc::new(MprisProxy::new(
+            event_bus_handle.clone(),
+            bus.clone(),
+        )));
+    }
+
+    if let Some(recording) = recording {
+        services.push(Arc::new(EventRecorder::new(
+            event_bus_handle.clone(),
+            recording.clone(),
+        )));
+    }
+
+    services
+}
+
+/// Feeds a `--record`ed session to PlayerManager, printing what Display would after each event,
+/// without touching D-Bus
+fn replay(
+    args: &Arc<Args>,
+    config: &Arc<Config>,
+    event_bus_handle: &EventBusHandle,
+    path: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let records = Recording::read(path)?;
+    info!("replaying {} records from {path:?}", records.len());
+
+    let player_manager = PlayerManager::new(
+        args.clone(),
+        event_bus_handle.clone(),
+        Arc::new(ReplayedQueries::new(&records)),
+    );
+    let display = Display::new(args.clone(), config.clone(), event_bus_handle.clone());
+    player_manager.replay(&records, |players| display.print_once(players));
+
+    info!("replay finished, stopping...");
+
+    Ok(())
+}
+
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let args = Arc::new(Args::parse());
 
-    // TODO: events, like sending signal to play/pause active player
-    init_logger(args.debug)?;
+    match &args.command {
+        Some(command) => commands::run(command, &args),
+        None => run_module(args),
+    }
+}
+
+/// Runs the module itself, as a bar's module, a daemon or a preview.
+/// Other subcommands talk to an already running instance, so only this may touch the log file
+fn run_module(args: Arc<Args>) -> Result<(), Box<dyn std::error::Error>> {
+    if args.once {
+        return commands::once::print(args);
+    }
+
+    // claimed before anything else, so a second daemon leaves the running one's log alone
+    let daemon_listener = if args.is_daemon() {
+        Some(daemon_socket::bind()?)
+    } else {
+        None
+    };
+
+    init_logger(
+        args.debug,
+        if args.is_daemon() {
+            "daemon.log"
+        } else if args.is_preview() {
+            "preview.log"
+        } else if args.replay.is_some() {
+            "replay.log"
+        } else {
+            "app.log"
+        },
+    )?;
 
     let config = match Config::new() {
         Ok(config) => Arc::new(config),
@@ -53,24 +245,74 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         event_bus.run();
     });
 
-    let dbus_client = Arc::new(DBusClient::new());
+    if let Some(path) = &args.replay {
+        return replay(&args, &config, &event_bus_handle, path);
+    }
 
-    let services: Vec<Arc<dyn Runnable>> = vec![
-        Arc::new(DBusMonitor::new(
-            args.clone(),
-            event_bus_handle.clone(),
-            dbus_client.clone(),
-        )),
-        Arc::new(PlayerManager::new(
+    let recording = match &args.record {
+        Some(path) => Some(Arc::new(Recording::create(path)?)),
+        None => None,
+    };
+
+    let mut services: Vec<Arc<dyn Runnable>> = if let Some(listener) = daemon_listener {
+        let mut services = backend_services(&args, &config, &event_bus_handle, recording.as_ref());
+        services.push(Arc::new(DaemonServer::new(
             event_bus_handle.clone(),
-            dbus_client.clone(),
-        )),
-        Arc::new(Display::new(
+            listener,
+        )));
+        services
+    } else {
+        let display = Arc::new(Display::new(
             args.clone(),
             config.clone(),
             event_bus_handle.clone(),
-        )),
-    ];
+        ));
+
+        if let Some(track) = commands::preview::fake_track(&args) {
+            // a made-up track needs nothing but the display
+            commands::preview::publish(&args, &event_bus_handle, vec![track]);
+            vec![display]
+        } else {
+            // a recording needs the players' own events, which a daemon doesn't pass on
+            let connection = match recording {
+                Some(_) => Err("recording the players".into()),
+                None => daemon_socket::connect(),
+            };
+            match connection {
+                Ok(connection) => {
+                    info!("rendering what the daemon sends");
+                    if args.has_selection_flags() {
+                        warn!("--whitelist, --selection and --priority are up to the daemon, ignoring ours while it runs");
+                    }
+
+                    let fallback = {
+                        let (args, config, event_bus_handle) =
+                            (args.clone(), config.clone(), event_bus_handle.clone());
+                        Box::new(move || backend_services(&args, &config, &event_bus_handle, None))
+                    };
+                    vec![
+                        display,
+                        Arc::new(DaemonClient::new(
+                            event_bus_handle.clone(),
+                            connection,
+                            fallback,
+                        )),
+                    ]
+                }
+                Err(err) => {
+                    info!("no daemon to connect to, running on our own: {err}");
+                    let mut services =
+                        backend_services(&args, &config, &event_bus_handle, recording.as_ref());
+                    services.push(display);
+                    services
+                }
+            }
+        }
+    };
+
+    if !args.is_daemon() && args.output == OutputMode::I3bar {
+        services.push(Arc::new(ClickListener::new(event_bus_handle.clone())));
+    }
 
     let mut handles = vec![];
     for service in services {
@@ -1,10 +1,15 @@
-use clap::Parser;
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand, ValueEnum};
 
 #[derive(Parser, Debug)]
 #[command(version)]
 pub struct Args {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
     /// Only monitor specified players, e.g "spotify firefox"
-    #[arg(short, long, value_delimiter = ' ')]
+    #[arg(short, long, value_delimiter = ' ', global = true)]
     pub whitelist: Vec<String>,
 
     /// Set play icon
@@ -19,6 +24,23 @@ pub struct Args {
     #[arg(short, long, default_value_t = String::from("[ %icon% ] %artist% - %title%"))]
     pub format: String,
 
+    /// Treat `--format`, `--player-format` and `--player-separator` as the output's markup,
+    /// like Pango for Waybar, rather than as plain text
+    #[arg(long, default_value_t = false)]
+    pub markup: bool,
+
+    /// Show every known player at once, each rendered with `--player-format`
+    #[arg(long, default_value_t = false)]
+    pub multi_player: bool,
+
+    /// Format string for each player when using `--multi-player`
+    #[arg(long, default_value_t = String::from("%icon% %artist% - %title%"))]
+    pub player_format: String,
+
+    /// Text placed between players when using `--multi-player`
+    #[arg(long, default_value_t = String::from(" | "))]
+    pub player_separator: String,
+
     /// Pause before restarting marquee, in ms
     #[arg(short, long, default_value_t = 0)]
     pub delay_marquee: u32,
@@ -39,6 +61,10 @@ pub struct Args {
     #[arg(short, long, default_value_t = String::new())]
     pub stopped_label: String,
 
+    /// Text to display while the bus the players are on can't be reached
+    #[arg(long, default_value_t = String::from("D-Bus disconnected"))]
+    pub error_label: String,
+
     /// Enable marquee scrolling on overflow
     #[arg(short, long, default_value_t = false)]
     pub marquee: bool,
@@ -47,7 +73,221 @@ pub struct Args {
     #[arg(long, default_value_t = false)]
     pub ellipsis: bool,
 
-    /// Enable debug logging
+    /// How to choose which player to display when several are running
+    #[arg(long, value_enum, default_value_t = SelectionMode::PreferPlaying)]
+    pub selection: SelectionMode,
+
+    /// Player order used by `--selection priority`, e.g "spotify mpv firefox"
+    #[arg(long, value_delimiter = ' ')]
+    pub priority: Vec<String>,
+
+    /// Print the current state once and exit, rather than following the players
     #[arg(long, default_value_t = false)]
+    pub once: bool,
+
+    /// With `--once`, print the full state of the displayed player as JSON instead of the format
+    #[arg(long, default_value_t = false, requires = "once")]
+    pub json: bool,
+
+    /// Address of the bus to find players on, instead of the session bus, like "unix:path=/tmp/bus"
+    #[arg(long, value_name = "ADDRESS", global = true)]
+    pub bus_address: Option<String>,
+
+    /// Write the players' events and answers to this file, to attach to a bug report
+    #[arg(long, value_name = "FILE", conflicts_with = "replay")]
+    pub record: Option<PathBuf>,
+
+    /// Play a `--record`ed session back without D-Bus, then exit
+    #[arg(long, value_name = "FILE")]
+    pub replay: Option<PathBuf>,
+
+    /// Which bar the output is for
+    #[arg(long, value_enum, default_value_t = OutputMode::Waybar)]
+    pub output: OutputMode,
+
+    /// Enable debug logging
+    #[arg(long, default_value_t = false, global = true)]
     pub debug: bool,
 }
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum OutputMode {
+    /// JSON for Waybar's custom module, with Pango markup
+    Waybar,
+    /// The i3bar protocol for i3bar and swaybar. Left click plays or pauses,
+    /// middle click goes to the previous track, right click to the next,
+    /// and scrolling switches between players
+    I3bar,
+    /// Text for Polybar's script module, with Polybar's formatting tags
+    Polybar,
+    /// Tags for yambar's script module
+    Yambar,
+    /// Just the text
+    Plain,
+    /// A JSON object per update with the full state of the displayed players
+    JsonStream,
+}
+
+#[derive(ValueEnum, Clone, Debug, PartialEq)]
+pub enum SelectionMode {
+    /// The most recently updated player
+    MostRecent,
+    /// Players listed in `--priority` first
+    Priority,
+    /// The most recently updated player that is playing, then the most recently paused
+    PreferPlaying,
+    /// Keep the current player until it stops
+    StickyUntilStopped,
+}
+
+impl Args {
+    pub fn is_daemon(&self) -> bool {
+        matches!(self.command, Some(Command::Daemon))
+    }
+
+    pub fn is_preview(&self) -> bool {
+        matches!(self.command, Some(Command::Preview { .. }))
+    }
+
+    /// Whether any of the flags that pick the displayed player were given
+    pub fn has_selection_flags(&self) -> bool {
+        !self.whitelist.is_empty()
+            || !self.priority.is_empty()
+            || self.selection != SelectionMode::PreferPlaying
+    }
+
+    /// Whether `--whitelist` lets us handle the player with this identity
+    pub fn is_whitelisted(&self, identity: &str) -> bool {
+        self.whitelist.is_empty()
+            || self
+                .whitelist
+                .iter()
+                .any(|w| identity.to_lowercase().contains(&w.to_lowercase()))
+    }
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Watch the players for every bar at once; bars started afterwards only render what it sends them
+    Daemon,
+
+    /// Keep showing the current player, ignoring events from other players
+    Pin,
+
+    /// Go back to showing the most recently active player
+    Unpin,
+
+    /// Change which player is displayed
+    Player {
+        #[command(subcommand)]
+        action: PlayerAction,
+    },
+
+    /// Render the format in the terminal, with its effects, width and the JSON Waybar would get.
+    /// Pass the same flags as your bar before `preview`, like `--format` or `--marquee`
+    Preview {
+        /// Show this made-up track instead of following the real players
+        #[arg(long)]
+        title: Option<String>,
+
+        /// Artist of the made-up track
+        #[arg(long)]
+        artist: Option<String>,
+
+        /// Album of the made-up track
+        #[arg(long)]
+        album: Option<String>,
+
+        /// Player name of the made-up track, to try out per-player icons
+        #[arg(long, default_value_t = String::from("preview"))]
+        player: String,
+
+        /// Length of the made-up track, in seconds
+        #[arg(long, default_value_t = 0)]
+        length: u64,
+    },
+
+    /// Pretend to be an MPRIS player going through a script of tracks, to reproduce bugs without real players
+    MockPlayer {
+        /// Registered as org.mpris.MediaPlayer2.<name>, which is also its identity
+        #[arg(long, default_value_t = String::from("mock"))]
+        name: String,
+
+        /// TOML file with the [[tracks]] to play, instead of the built-in ones with missing albums,
+        /// huge titles, CJK and RTL text and a live stream
+        #[arg(long)]
+        script: Option<PathBuf>,
+
+        /// Seconds to play each track for, unless it is shorter or sets `play_for`
+        #[arg(long, default_value_t = 10)]
+        step: u64,
+    },
+
+    /// Check the session bus, config and directories the module depends on, to paste into an issue.
+    /// Pass the same `--format` as your bar to check its placeholders too
+    Doctor,
+
+    /// List every MPRIS player on the bus and how the module sees it, to debug `--whitelist` and icons
+    Players {
+        /// Print the players as JSON instead of a table
+        #[arg(long, default_value_t = false)]
+        json: bool,
+    },
+
+    /// Show the recently played tracks, see [history] in config.toml
+    History {
+        /// How many of the most recent plays to show
+        #[arg(short = 'n', long, default_value_t = 20)]
+        limit: usize,
+
+        /// Print the plays in this format instead of a table
+        #[arg(long, value_enum)]
+        export: Option<ExportFormat>,
+    },
+
+    /// Show what you listened to the most, from the history recorded with [history] in config.toml
+    Stats {
+        /// How far back to look
+        #[arg(long, value_enum, default_value_t = StatsPeriod::Week)]
+        period: StatsPeriod,
+
+        /// How many entries to show in each top list
+        #[arg(short = 'n', long, default_value_t = 10)]
+        limit: usize,
+    },
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum StatsPeriod {
+    /// Since midnight
+    Day,
+    /// The last 7 days, including today
+    Week,
+    /// The last 30 days, including today
+    Month,
+}
+
+impl StatsPeriod {
+    pub fn days(&self) -> u64 {
+        match self {
+            StatsPeriod::Day => 1,
+            StatsPeriod::Week => 7,
+            StatsPeriod::Month => 30,
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum ExportFormat {
+    Csv,
+    M3u,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum PlayerAction {
+    /// Display the next known player
+    Next,
+
+    /// Display the previous known player
+    Prev,
+}
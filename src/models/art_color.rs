@@ -0,0 +1,60 @@
+use bincode::{Decode, Encode};
+
+/// Saturation below which a color is considered grey, and its hue meaningless
+const MIN_SATURATION: f32 = 0.15;
+
+/// The most prominent color of an album cover
+#[derive(Debug, Clone, Copy, Encode, Decode, PartialEq)]
+pub struct ArtColor {
+    pub red: u8,
+    pub green: u8,
+    pub blue: u8,
+}
+
+impl ArtColor {
+    /// Like `#1db954`, usable in Pango markup
+    pub fn hex(&self) -> String {
+        format!("#{:02x}{:02x}{:02x}", self.red, self.green, self.blue)
+    }
+
+    /// Hue and saturation, with the hue in degrees
+    fn hue_saturation(&self) -> (f32, f32) {
+        let (r, g, b) = (
+            self.red as f32 / 255.0,
+            self.green as f32 / 255.0,
+            self.blue as f32 / 255.0,
+        );
+        let max = r.max(g).max(b);
+        let min = r.min(g).min(b);
+        let delta = max - min;
+
+        let saturation = if max == 0.0 { 0.0 } else { delta / max };
+        let hue = if delta == 0.0 {
+            0.0
+        } else if max == r {
+            60.0 * ((g - b) / delta).rem_euclid(6.0)
+        } else if max == g {
+            60.0 * ((b - r) / delta + 2.0)
+        } else {
+            60.0 * ((r - g) / delta + 4.0)
+        };
+
+        (hue, saturation)
+    }
+
+    /// A CSS class for the color, with the hue rounded to steps of 30 degrees like `art-hue-210`,
+    /// or `art-grey` for colors without much of a hue
+    pub fn css_class(&self) -> String {
+        let (hue, saturation) = self.hue_saturation();
+        if saturation < MIN_SATURATION {
+            return String::from("art-grey");
+        }
+
+        format!("art-hue-{}", ((hue / 30.0).round() as u16 % 12) * 30)
+    }
+
+    /// How colorful the color is, used to prefer accent colors over greys
+    pub fn saturation(&self) -> f32 {
+        self.hue_saturation().1
+    }
+}
@@ -0,0 +1,9 @@
+use bincode::{Decode, Encode};
+
+/// Whether we can reach the bus the players are on
+#[derive(Debug, Clone, Encode, Decode, PartialEq)]
+pub enum BusStatus {
+    Connected,
+    /// Lost or never got the connection, with the reason why
+    Disconnected(String),
+}
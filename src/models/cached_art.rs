@@ -0,0 +1,14 @@
+use bincode::{Decode, Encode};
+
+use crate::models::art_color::ArtColor;
+
+/// Album art that was copied into the local art cache
+#[derive(Debug, Clone, Encode, Decode, PartialEq)]
+pub struct CachedArt {
+    /// The URL the player gave us
+    pub art_url: String,
+    /// Where the art is stored on disk
+    pub path: String,
+    /// None when the image couldn't be decoded
+    pub color: Option<ArtColor>,
+}
@@ -0,0 +1,24 @@
+use serde::Deserialize;
+
+use super::control_command::ControlCommand;
+
+/// A click on our block, as sent by i3bar and swaybar on stdin.
+/// Only the button matters to us, everything else about the click is ignored
+#[derive(Debug, Deserialize)]
+pub struct ClickEvent {
+    pub button: u8,
+}
+
+impl ClickEvent {
+    pub fn command(&self) -> Option<ControlCommand> {
+        match self.button {
+            1 => Some(ControlCommand::PlayPause),
+            2 => Some(ControlCommand::Previous),
+            3 => Some(ControlCommand::Next),
+            // scrolling up and down
+            4 => Some(ControlCommand::PreviousPlayer),
+            5 => Some(ControlCommand::NextPlayer),
+            _ => None,
+        }
+    }
+}
@@ -11,6 +11,26 @@ use crate::helpers;
 #[derive(Debug, Deserialize, Serialize, Default)]
 pub struct Config {
     icons: Icons,
+    #[serde(default)]
+    hooks: Hooks,
+    #[serde(default)]
+    notifications: Notifications,
+    #[serde(default)]
+    history: History,
+    #[serde(default)]
+    scrobbler: Scrobbler,
+    #[serde(default)]
+    now_playing: NowPlayingExport,
+    #[serde(default)]
+    art_cache: ArtCacheConfig,
+    #[serde(default)]
+    lyrics: LyricsConfig,
+    #[serde(default)]
+    dbus_service: DBusServiceConfig,
+    #[serde(default)]
+    mpris_proxy: MprisProxyConfig,
+    #[serde(default)]
+    bus: BusConfig,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -29,12 +49,219 @@ impl Default for Icons {
     }
 }
 
+/// Shell commands to run when the displayed player changes
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct Hooks {
+    pub on_track_change: Option<String>,
+    pub on_state_change: Option<String>,
+    pub on_player_change: Option<String>,
+    /// How long things must stay unchanged before hooks run, so skipping through tracks only runs them once
+    pub debounce_ms: u64,
+    /// Hooks still running after this long are killed
+    pub timeout_ms: u64,
+}
+
+impl Default for Hooks {
+    fn default() -> Self {
+        Self {
+            on_track_change: None,
+            on_state_change: None,
+            on_player_change: None,
+            debounce_ms: 500,
+            timeout_ms: 10000,
+        }
+    }
+}
+
+impl Hooks {
+    pub fn is_empty(&self) -> bool {
+        self.on_track_change.is_none()
+            && self.on_state_change.is_none()
+            && self.on_player_change.is_none()
+    }
+}
+
+/// Desktop notifications sent when the displayed track changes
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct Notifications {
+    pub enabled: bool,
+    pub summary: String,
+    pub body: String,
+    /// Only notify for players partially matching one of these, notify for all players if empty
+    pub players: Vec<String>,
+    /// How long a track must be playing before we notify, so skipping through tracks doesn't spam notifications
+    pub debounce_ms: u64,
+    /// How long the notification is shown, -1 lets the notification server decide
+    pub expire_timeout_ms: i32,
+}
+
+impl Default for Notifications {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            summary: String::from("%title%"),
+            body: String::from("%artist% - %album%"),
+            players: vec![],
+            debounce_ms: 1000,
+            expire_timeout_ms: -1,
+        }
+    }
+}
+
+impl Notifications {
+    pub fn should_notify_for(&self, player_name: &str) -> bool {
+        self.players.is_empty()
+            || self
+                .players
+                .iter()
+                .any(|p| player_name.to_lowercase().contains(&p.to_lowercase()))
+    }
+}
+
+/// Local listening history, see `waybar-module-music history`
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct History {
+    pub enabled: bool,
+    /// A track counts as played after playing this percentage of its length...
+    pub min_percent: u8,
+    /// ...or this many seconds, whichever comes first
+    pub min_seconds: u64,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            min_percent: 50,
+            min_seconds: 240,
+        }
+    }
+}
+
+/// Submitting plays to ListenBrainz, or anything speaking its API like Maloja
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct Scrobbler {
+    pub enabled: bool,
+    /// Root of the API, `/1/submit-listens` is appended to it
+    pub endpoint: String,
+    pub token: String,
+    /// Also tell the server what's currently playing, not only finished tracks
+    pub now_playing: bool,
+}
+
+impl Default for Scrobbler {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            endpoint: String::from("https://api.listenbrainz.org"),
+            token: String::new(),
+            now_playing: true,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum NowPlayingFormat {
+    /// Every field of the displayed player
+    Json,
+    /// Only the rendered template
+    Text,
+}
+
+/// Writing the displayed player to a file on every change, for overlays, conky and the like
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct NowPlayingExport {
+    pub enabled: bool,
+    pub format: NowPlayingFormat,
+    /// Used by the text format, accepts the same placeholders as notifications
+    pub template: String,
+    /// Where to write, defaults to now-playing.json or now-playing.txt in $XDG_RUNTIME_DIR/waybar-module-music
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<PathBuf>,
+}
+
+impl Default for NowPlayingExport {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            format: NowPlayingFormat::Json,
+            template: String::from("%artist% - %title%"),
+            path: None,
+        }
+    }
+}
+
+/// Keeping local copies of album art, for %art-path% and Waybar's image module
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct ArtCacheConfig {
+    pub enabled: bool,
+    /// The least recently shown art is removed once the cache grows past this
+    pub max_size_mb: u64,
+}
+
+impl Default for ArtCacheConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_size_mb: 50,
+        }
+    }
+}
+
+/// Synced lyrics from local .lrc files, for %lyric%
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+#[serde(default)]
+pub struct LyricsConfig {
+    pub enabled: bool,
+    /// Directories with lyrics named like `Artist - Title.lrc`, besides the ones next to the music itself
+    pub directories: Vec<String>,
+}
+
+/// Serving the displayed player on the session bus, as io.github.waybar_module_music
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct DBusServiceConfig {
+    pub enabled: bool,
+}
+
+impl Default for DBusServiceConfig {
+    fn default() -> Self {
+        Self { enabled: true }
+    }
+}
+
+/// Re-exposing the displayed player as org.mpris.MediaPlayer2.waybar_active
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+#[serde(default)]
+pub struct MprisProxyConfig {
+    pub enabled: bool,
+}
+
+/// The bus to find the players on, when it isn't the session bus. `--bus-address` takes precedence
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+#[serde(default)]
+pub struct BusConfig {
+    /// Like "unix:path=/run/user/1000/container-bus"
+    pub address: Option<String>,
+}
+
 static EMPTY_STRING: String = String::new();
 
 impl Config {
+    /// Where config.toml lives, creating its directory if needed
+    pub fn path() -> Result<PathBuf, std::io::Error> {
+        Ok(helpers::dir::get_and_create_dir(dirs::config_dir)?.join("config.toml"))
+    }
+
     pub fn new() -> Result<Self, ConfigError> {
-        let config_dir = helpers::dir::get_and_create_dir(dirs::config_dir)?;
-        let config_path = config_dir.join("config.toml");
+        let config_path = Config::path()?;
 
         if File::create_new(&config_path).is_ok() {
             Config::create_default_config_file(&config_path)?;
@@ -46,14 +273,46 @@ impl Config {
     }
 
     fn create_default_config_file(path: &PathBuf) -> Result<(), ConfigError> {
-        let doc_string = r"# You can configure unique text to display for any given player
+        let doc_string = r#"# You can configure unique text to display for any given player
 # It works by doing a partial match against a players name
 # So for Firefox for example, which is advertised as 'Mozilla Firefox', you'd want something like this:
 #
 # [icons.players]
 # mozilla = 'icon'
 #
-# Even 'moz' would work, but obviously more specific strings are better to ensure the correct player is matched";
+# Even 'moz' would work, but obviously more specific strings are better to ensure the correct player is matched
+#
+# You can also run your own commands when the displayed track, playback state or player changes,
+# by adding any of these to the [hooks] section below
+# Details about the track are passed as environment variables, like $WAYBAR_MUSIC_TITLE
+#
+# on_track_change = 'notify-send "$WAYBAR_MUSIC_ARTIST" "$WAYBAR_MUSIC_TITLE"'
+# on_state_change = 'echo "$WAYBAR_MUSIC_STATUS" >> ~/music.log'
+# on_player_change = ''
+#
+# Desktop notifications for track changes can be enabled in the [notifications] section below
+# The summary and body accept the same placeholders as --format, like %artist% and %title%
+#
+# Enable [history] below to keep a local record of what you listen to, see `waybar-module-music history`
+#
+# Enable [scrobbler] below and set your token to submit what you listen to ListenBrainz
+#
+# Enable [now_playing] below to write the current track to a file other programs can read,
+# as JSON or as text using the template. Set `path` to choose where it's written
+#
+# Enable [art_cache] below to keep local copies of album art, for %art-path% and Waybar's image module
+#
+# Enable [lyrics] below to show the current line of .lrc lyrics with %lyric%
+# Lyrics are looked up next to the playing file, then as 'Artist - Title.lrc' in `directories`, like ['~/Music/lyrics']
+#
+# The displayed player is also served on D-Bus as io.github.waybar_module_music,
+# set `enabled = false` in [dbus_service] below to turn that off
+#
+# Enable [mpris_proxy] below to follow the displayed player with any MPRIS client,
+# like `playerctl -p waybar_active play-pause` for media keys
+#
+# Players are looked for on the session bus. To follow players on another bus, like one in a container,
+# set `address = 'unix:path=...'` in [bus] below"#;
         fs::write(
             path,
             format!("{}\n{}", doc_string, toml::to_string(&Config::default())?),
@@ -61,11 +320,57 @@ impl Config {
         Ok(())
     }
 
+    pub fn hooks(&self) -> &Hooks {
+        &self.hooks
+    }
+
+    pub fn notifications(&self) -> &Notifications {
+        &self.notifications
+    }
+
+    pub fn history(&self) -> &History {
+        &self.history
+    }
+
+    pub fn scrobbler(&self) -> &Scrobbler {
+        &self.scrobbler
+    }
+
+    pub fn now_playing(&self) -> &NowPlayingExport {
+        &self.now_playing
+    }
+
+    pub fn art_cache(&self) -> &ArtCacheConfig {
+        &self.art_cache
+    }
+
+    pub fn lyrics(&self) -> &LyricsConfig {
+        &self.lyrics
+    }
+
+    pub fn dbus_service(&self) -> &DBusServiceConfig {
+        &self.dbus_service
+    }
+
+    pub fn mpris_proxy(&self) -> &MprisProxyConfig {
+        &self.mpris_proxy
+    }
+
+    pub fn bus(&self) -> &BusConfig {
+        &self.bus
+    }
+
+    /// The `[icons.players]` entry whose key is part of the player's name, as (key, icon)
+    pub fn find_player_icon_rule(&self, player_name: &str) -> Option<(&String, &String)> {
+        self.icons
+            .players
+            .iter()
+            .find(|(k, _)| player_name.to_lowercase().contains(&k.to_lowercase()))
+    }
+
     pub fn get_player_icon_by_partial_match(&self, player_name: &str) -> &String {
-        for (k, v) in self.icons.players.iter() {
-            if player_name.to_lowercase().contains(&k.to_lowercase()) {
-                return v;
-            }
+        if let Some((_, icon)) = self.find_player_icon_rule(player_name) {
+            return icon;
         }
         self.icons.players.get("default").unwrap_or_else(|| {
             log::warn!("Failed to get default player icon! Has the default key-value been deleted? Defaulting to blank value");
@@ -0,0 +1,37 @@
+use bincode::{Decode, Encode};
+
+/// Commands sent to a running instance, e.g from `waybar-module-music pin`
+#[derive(Debug, Clone, Encode, Decode, PartialEq)]
+pub enum ControlCommand {
+    /// Keep showing the currently displayed player, ignoring events from other players
+    Pin,
+    Unpin,
+    NextPlayer,
+    PreviousPlayer,
+    /// Playback controls, sent to the displayed player
+    PlayPause,
+    Play,
+    Pause,
+    Next,
+    Previous,
+}
+
+impl std::fmt::Display for ControlCommand {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                ControlCommand::Pin => "pin",
+                ControlCommand::Unpin => "unpin",
+                ControlCommand::NextPlayer => "player next",
+                ControlCommand::PreviousPlayer => "player prev",
+                ControlCommand::PlayPause => "play-pause",
+                ControlCommand::Play => "play",
+                ControlCommand::Pause => "pause",
+                ControlCommand::Next => "next",
+                ControlCommand::Previous => "previous",
+            }
+        )
+    }
+}
@@ -0,0 +1,27 @@
+use bincode::{Decode, Encode};
+
+use crate::models::play::Play;
+
+/// Summary of today's listening, shown through the `%today-*%` placeholders
+#[derive(Debug, Clone, Default, Encode, Decode, PartialEq)]
+pub struct ListeningStats {
+    /// Time spent listening, in seconds
+    pub listened: u64,
+    pub tracks: u32,
+}
+
+impl ListeningStats {
+    pub fn from_plays<'a>(plays: impl IntoIterator<Item = &'a Play>) -> Self {
+        plays
+            .into_iter()
+            .fold(ListeningStats::default(), |mut stats, play| {
+                stats.add(play);
+                stats
+            })
+    }
+
+    pub fn add(&mut self, play: &Play) {
+        self.listened += play.listened;
+        self.tracks += 1;
+    }
+}
@@ -0,0 +1,194 @@
+use bincode::{Decode, Encode};
+
+#[derive(Debug, Clone, Encode, Decode, PartialEq)]
+pub struct LyricLine {
+    /// When the line starts, in milliseconds from the start of the track
+    pub time_ms: u64,
+    pub text: String,
+}
+
+/// Timed lyrics parsed from an LRC file, ordered by time
+#[derive(Debug, Clone, Default, Encode, Decode, PartialEq)]
+pub struct Lyrics {
+    lines: Vec<LyricLine>,
+}
+
+impl Lyrics {
+    /// Parse timed LRC, where every line starts with one or more timestamps like `[01:23.45]`.
+    /// Untimed lines and metadata tags are skipped, except for `[offset:]` which shifts every line
+    pub fn parse(text: &str) -> Self {
+        let mut offset_ms: i64 = 0;
+        let mut lines = vec![];
+
+        for line in text.trim_start_matches('\u{feff}').lines() {
+            let mut rest = line.trim();
+            let mut times = vec![];
+
+            while let Some((tag, after)) =
+                rest.strip_prefix('[').and_then(|tag| tag.split_once(']'))
+            {
+                if let Some(time) = Lyrics::parse_timestamp(tag) {
+                    times.push(time);
+                } else if let Some(offset) = tag.strip_prefix("offset:") {
+                    offset_ms = offset.trim().parse().unwrap_or(0);
+                }
+                rest = after;
+            }
+
+            let text = Lyrics::strip_word_timestamps(rest.trim());
+            for time in times {
+                lines.push((time, text.clone()));
+            }
+        }
+
+        // a positive offset makes the lyrics show up sooner
+        let mut lines: Vec<LyricLine> = lines
+            .into_iter()
+            .map(|(time, text)| LyricLine {
+                time_ms: (time as i64 - offset_ms).max(0) as u64,
+                text,
+            })
+            .collect();
+        lines.sort_by_key(|line| line.time_ms);
+
+        Self { lines }
+    }
+
+    /// Timestamps look like `mm:ss`, `mm:ss.xx`, `mm:ss.xxx` or `mm:ss:xx`
+    fn parse_timestamp(tag: &str) -> Option<u64> {
+        let (minutes, seconds) = tag.split_once(':')?;
+        let minutes: u64 = minutes.trim().parse().ok()?;
+
+        let (seconds, fraction) = match seconds.split_once(['.', ':']) {
+            Some((seconds, fraction)) => (seconds, fraction),
+            None => (seconds, "0"),
+        };
+        let seconds: u64 = seconds.trim().parse().ok()?;
+        if !fraction.chars().all(|c| c.is_ascii_digit()) || fraction.is_empty() {
+            return None;
+        }
+        // ".5" is half a second, ".05" is 50 milliseconds
+        let fraction_ms = format!("{fraction:0<3}")[..3].parse::<u64>().ok()?;
+
+        Some((minutes * 60 + seconds) * 1000 + fraction_ms)
+    }
+
+    /// Enhanced LRC marks every word with a timestamp like `<01:23.45>`, which we don't show
+    fn strip_word_timestamps(text: &str) -> String {
+        let mut stripped = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(start) = rest.find('<') {
+            match rest[start..].find('>') {
+                Some(end) if Lyrics::parse_timestamp(&rest[start + 1..start + end]).is_some() => {
+                    stripped.push_str(&rest[..start]);
+                    rest = &rest[start + end + 1..];
+                }
+                _ => {
+                    stripped.push_str(&rest[..=start]);
+                    rest = &rest[start + 1..];
+                }
+            }
+        }
+        stripped.push_str(rest);
+        stripped.trim().to_string()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+
+    /// The line being sung at the given position, or None before the first line
+    pub fn line_at(&self, position_ms: u64) -> Option<&str> {
+        let index = self
+            .lines
+            .partition_point(|line| line.time_ms <= position_ms);
+        index
+            .checked_sub(1)
+            .map(|index| self.lines[index].text.as_str())
+    }
+
+    /// When the line after the given position starts, to know when to redraw
+    pub fn next_line_ms(&self, position_ms: u64) -> Option<u64> {
+        self.lines
+            .get(
+                self.lines
+                    .partition_point(|line| line.time_ms <= position_ms),
+            )
+            .map(|line| line.time_ms)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn times(lyrics: &Lyrics) -> Vec<(u64, &str)> {
+        lyrics
+            .lines
+            .iter()
+            .map(|line| (line.time_ms, line.text.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn timestamp_fractions_use_dots_or_colons() {
+        assert_eq!(Lyrics::parse_timestamp("01:23.45"), Some(83_450));
+        assert_eq!(Lyrics::parse_timestamp("01:23:45"), Some(83_450));
+        assert_eq!(Lyrics::parse_timestamp("01:23.456"), Some(83_456));
+        assert_eq!(Lyrics::parse_timestamp("01:23.5"), Some(83_500));
+        assert_eq!(Lyrics::parse_timestamp("01:23"), Some(83_000));
+        assert_eq!(Lyrics::parse_timestamp("ar:Artist"), None);
+        assert_eq!(Lyrics::parse_timestamp("01:23."), None);
+    }
+
+    #[test]
+    fn metadata_and_untimed_lines_are_skipped() {
+        let lyrics = Lyrics::parse("[ar:Artist]\n[ti:Title]\nno time here\n[00:01.00]First\n");
+        assert_eq!(times(&lyrics), vec![(1000, "First")]);
+    }
+
+    #[test]
+    fn line_with_several_timestamps_repeats() {
+        let lyrics = Lyrics::parse("[00:10.00][00:30.00]Chorus\n[00:20.00]Verse\n");
+        assert_eq!(
+            times(&lyrics),
+            vec![(10_000, "Chorus"), (20_000, "Verse"), (30_000, "Chorus")]
+        );
+    }
+
+    #[test]
+    fn positive_offset_shows_lines_sooner() {
+        let lyrics = Lyrics::parse("[offset:+500]\n[00:00.20]Start\n[00:02.00]Next\n");
+        assert_eq!(times(&lyrics), vec![(0, "Start"), (1500, "Next")]);
+
+        let lyrics = Lyrics::parse("[offset:-500]\n[00:02.00]Later\n");
+        assert_eq!(times(&lyrics), vec![(2500, "Later")]);
+    }
+
+    #[test]
+    fn word_timestamps_are_stripped() {
+        let lyrics =
+            Lyrics::parse("[00:01.00]<00:01.00>Hello <00:01.50>world <3\n[00:02.00]a<b>c\n");
+        assert_eq!(
+            times(&lyrics),
+            vec![(1000, "Hello world <3"), (2000, "a<b>c")]
+        );
+    }
+
+    #[test]
+    fn line_at_follows_the_position() {
+        let lyrics = Lyrics::parse("[00:01.00]One\n[00:03.00]Two\n");
+        assert_eq!(lyrics.line_at(500), None);
+        assert_eq!(lyrics.line_at(1000), Some("One"));
+        assert_eq!(lyrics.line_at(2999), Some("One"));
+        assert_eq!(lyrics.line_at(60_000), Some("Two"));
+    }
+
+    #[test]
+    fn next_line_is_the_one_after_the_position() {
+        let lyrics = Lyrics::parse("[00:01.00]One\n[00:03.00]Two\n");
+        assert_eq!(lyrics.next_line_ms(0), Some(1000));
+        assert_eq!(lyrics.next_line_ms(1000), Some(3000));
+        assert_eq!(lyrics.next_line_ms(3000), None);
+    }
+}
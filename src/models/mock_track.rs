@@ -0,0 +1,119 @@
+use dbus::{
+    arg::{PropMap, Variant},
+    Path,
+};
+use serde::Deserialize;
+
+/// The tracks `waybar-module-music mock-player` plays through, read from a TOML file like
+///
+/// ```toml
+/// [[tracks]]
+/// title = "Some title"
+/// artist = "Someone"
+/// length = 200
+/// ```
+#[derive(Debug, Deserialize)]
+pub struct MockScript {
+    pub tracks: Vec<MockTrack>,
+}
+
+#[derive(Debug, Deserialize, Default, Clone)]
+#[serde(default)]
+pub struct MockTrack {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    /// In seconds. Live streams have none
+    pub length: Option<u64>,
+    pub url: Option<String>,
+    pub art_url: Option<String>,
+    /// Playback rate while this track plays, announced when it starts
+    pub rate: Option<f64>,
+    /// Seconds to stay on this track before moving on, instead of `--step`
+    pub play_for: Option<u64>,
+}
+
+impl MockTrack {
+    fn new(title: &str, artist: &str, album: Option<&str>, length: Option<u64>) -> Self {
+        Self {
+            title: Some(title.to_string()),
+            artist: Some(artist.to_string()),
+            album: album.map(str::to_string),
+            length,
+            ..Default::default()
+        }
+    }
+
+    /// Tracks that have tripped up the module before, or could
+    pub fn defaults() -> Vec<MockTrack> {
+        vec![
+            MockTrack::new(
+                "Ordinary Track",
+                "Mock Artist",
+                Some("Mock Album"),
+                Some(200),
+            ),
+            MockTrack::new("No Album Here", "Mock Artist", None, Some(180)),
+            MockTrack::new(
+                &"An Extraordinarily Long Title That Keeps Going ".repeat(8),
+                "Somebody With A Rather Long Name Featuring Several Others",
+                Some("Deluxe Remastered Anniversary Edition"),
+                Some(420),
+            ),
+            MockTrack::new("夜に駆ける", "YOASOBI", Some("THE BOOK"), Some(261)),
+            MockTrack::new("لما بدا يتثنى", "فيروز", Some("أندلسيات"), Some(240)),
+            MockTrack {
+                title: Some(String::from("Mock Radio - Live")),
+                url: Some(String::from("http://stream.example.com/live.mp3")),
+                ..Default::default()
+            },
+            MockTrack {
+                rate: Some(1.5),
+                ..MockTrack::new("Podcast at 1.5x", "Mock Podcast", None, Some(3600))
+            },
+            MockTrack::new(
+                "Special <Characters> & \"Quotes\"",
+                "It's Me",
+                None,
+                Some(150),
+            ),
+        ]
+    }
+
+    /// The track as MPRIS `Metadata`
+    pub fn metadata(&self, index: usize) -> PropMap {
+        let mut metadata = PropMap::new();
+        let track_id = Path::from(format!("/org/mpris/MediaPlayer2/Track/{index}"));
+        metadata.insert("mpris:trackid".to_string(), Variant(Box::new(track_id)));
+
+        if let Some(title) = &self.title {
+            metadata.insert("xesam:title".to_string(), Variant(Box::new(title.clone())));
+        }
+        if let Some(artist) = &self.artist {
+            metadata.insert(
+                "xesam:artist".to_string(),
+                Variant(Box::new(vec![artist.clone()])),
+            );
+        }
+        if let Some(album) = &self.album {
+            metadata.insert("xesam:album".to_string(), Variant(Box::new(album.clone())));
+        }
+        if let Some(length) = self.length {
+            metadata.insert(
+                "mpris:length".to_string(),
+                Variant(Box::new(length as i64 * 1000 * 1000)),
+            );
+        }
+        if let Some(url) = &self.url {
+            metadata.insert("xesam:url".to_string(), Variant(Box::new(url.clone())));
+        }
+        if let Some(art_url) = &self.art_url {
+            metadata.insert(
+                "mpris:artUrl".to_string(),
+                Variant(Box::new(art_url.clone())),
+            );
+        }
+
+        metadata
+    }
+}
@@ -1,11 +1,24 @@
 pub mod args;
+pub mod art_color;
+pub mod bus_status;
+pub mod cached_art;
+pub mod click_event;
 pub mod config;
+pub mod control_command;
+pub mod listening_stats;
+pub mod lyrics;
+pub mod mock_track;
 pub mod mpris_identity;
 pub mod mpris_metadata;
 pub mod mpris_playback;
 pub mod mpris_rate;
 pub mod mpris_seeked;
+pub mod now_playing;
+pub mod play;
+pub mod play_tracker;
 pub mod playback_state;
 pub mod player_client;
+pub mod player_info;
 pub mod player_state;
 pub mod player_timer;
+pub mod track_lyrics;
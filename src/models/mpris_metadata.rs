@@ -20,7 +20,8 @@ pub struct MprisMetadata {
     pub length: Option<u64>,
     comment: Vec<String>,
     track_id: Option<String>,
-    art_url: Option<String>,
+    pub art_url: Option<String>,
+    pub url: Option<String>,
 }
 
 impl MprisMetadata {
@@ -41,6 +42,7 @@ impl MprisMetadata {
             comment: vec![],
             track_id: None,
             art_url: None,
+            url: None,
         }
     }
 
@@ -79,6 +81,7 @@ impl MprisMetadata {
             "mpris:length" => self.length = value.as_i64().map(|elem| elem as u64),
             "mpris:trackid" => self.track_id = MprisMetadata::refarg_to_string(value),
             "mpris:artUrl" => self.art_url = MprisMetadata::refarg_to_string(value),
+            "xesam:url" => self.url = MprisMetadata::refarg_to_string(value),
             _ => (),
         }
     }
@@ -0,0 +1,62 @@
+use serde::Serialize;
+
+use crate::models::{
+    cached_art::CachedArt, playback_state::PlaybackState, player_state::PlayerState,
+};
+
+/// The displayed player as written for other programs, see [now_playing] in config.toml.
+/// Kept separate from `PlayerState` so its field names stay stable for scripts
+#[derive(Debug, Serialize)]
+pub struct NowPlaying {
+    pub player: String,
+    pub player_id: String,
+    pub status: String,
+    pub artist: String,
+    pub title: String,
+    pub album: String,
+    /// Track length in seconds, 0 if unknown
+    pub length: u64,
+    /// Playback position in seconds
+    pub position: u64,
+    pub art_url: String,
+    /// Local copy of the art, when the art cache is enabled
+    pub art_path: String,
+    /// Dominant color of the art like `#1db954`, when the art cache is enabled
+    pub art_color: String,
+    pub url: String,
+    pub pinned: bool,
+}
+
+impl NowPlaying {
+    pub fn with_art(mut self, art: Option<&CachedArt>) -> Self {
+        if let Some(art) = art {
+            self.art_path = art.path.clone();
+            self.art_color = art.color.map(|color| color.hex()).unwrap_or_default();
+        }
+        self
+    }
+}
+
+impl From<&PlayerState> for NowPlaying {
+    fn from(state: &PlayerState) -> Self {
+        Self {
+            player: state.player_name.clone(),
+            player_id: state.player_id.clone(),
+            status: state
+                .playing
+                .clone()
+                .unwrap_or(PlaybackState::Stopped)
+                .to_string(),
+            artist: state.artist.clone(),
+            title: state.title.clone(),
+            album: state.album.clone(),
+            length: state.length / 1000 / 1000,
+            position: (state.position / 1000 / 1000) as u64,
+            art_url: state.art_url.clone(),
+            art_path: String::new(),
+            art_color: String::new(),
+            url: state.url.clone(),
+            pinned: state.pinned,
+        }
+    }
+}
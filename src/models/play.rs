@@ -0,0 +1,18 @@
+use serde::{Deserialize, Serialize};
+
+/// A track that was listened to long enough to count as played
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Play {
+    /// When the track started playing, as a UNIX timestamp
+    pub timestamp: u64,
+    pub player: String,
+    pub artist: String,
+    pub title: String,
+    pub album: String,
+    /// Track length in seconds, 0 if unknown
+    pub length: u64,
+    /// How long the track was actually playing, in seconds
+    pub listened: u64,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub url: String,
+}
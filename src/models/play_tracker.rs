@@ -0,0 +1,186 @@
+use std::time::{Duration, Instant};
+
+use crate::{
+    models::{play::Play, playback_state::PlaybackState, player_state::PlayerState},
+    utils::time::get_current_timestamp,
+};
+
+/// Tracks shorter than this never count as played
+const MIN_TRACK_LENGTH: Duration = Duration::from_secs(30);
+
+/// Going back to the start of the same track after this long counts as playing it again
+const REPLAY_POSITION: Duration = Duration::from_secs(5);
+
+/// How long a track must play to count as listened to
+struct PlayThreshold {
+    min_percent: u8,
+    min_listened: Duration,
+}
+
+impl PlayThreshold {
+    fn is_met(&self, length: u64, listened: Duration) -> bool {
+        let length = Duration::from_micros(length);
+        if !length.is_zero() && length < MIN_TRACK_LENGTH {
+            return false;
+        }
+
+        listened >= self.min_listened
+            || (!length.is_zero()
+                && listened.as_secs_f64() >= length.as_secs_f64() * self.min_percent as f64 / 100.0)
+    }
+}
+
+struct TrackedPlay {
+    state: PlayerState,
+    started_at: u64,
+    listened: Duration,
+    last_update: Instant,
+}
+
+impl TrackedPlay {
+    /// Time listened so far, including the stretch since the last update if it's still playing
+    fn listened_until(&self, now: Instant) -> Duration {
+        if self.state.playing == Some(PlaybackState::Playing) {
+            self.listened + now.duration_since(self.last_update)
+        } else {
+            self.listened
+        }
+    }
+
+    fn to_play(&self, listened: Duration) -> Play {
+        Play {
+            timestamp: self.started_at,
+            player: self.state.player_name.clone(),
+            artist: self.state.artist.clone(),
+            title: self.state.title.clone(),
+            album: self.state.album.clone(),
+            length: self.state.length / 1000 / 1000,
+            listened: listened.as_secs(),
+            url: self.state.url.clone(),
+        }
+    }
+}
+
+/// Follows the displayed player to figure out which tracks were actually listened to.
+/// A track counts once it played for `min_percent` of its length or `min_listened`, whichever comes first
+pub struct PlayTracker {
+    threshold: PlayThreshold,
+    current: Option<TrackedPlay>,
+}
+
+impl PlayTracker {
+    pub fn new(min_percent: u8, min_listened: Duration) -> Self {
+        Self {
+            threshold: PlayThreshold {
+                min_percent,
+                min_listened,
+            },
+            current: None,
+        }
+    }
+
+    fn is_same_track(a: &PlayerState, b: &PlayerState) -> bool {
+        a.player_id == b.player_id
+            && a.artist == b.artist
+            && a.title == b.title
+            && a.album == b.album
+    }
+
+    /// Feed the latest state of the displayed player.
+    /// Returns the previous track if it just ended and was listened to long enough
+    pub fn update(&mut self, state: &PlayerState) -> Option<Play> {
+        let now = Instant::now();
+
+        if let Some(current) = &mut self.current {
+            current.listened = current.listened_until(now);
+            current.last_update = now;
+
+            let replayed = state.position < REPLAY_POSITION.as_micros()
+                && current.state.position > state.position + REPLAY_POSITION.as_micros()
+                && self
+                    .threshold
+                    .is_met(current.state.length, current.listened);
+
+            if PlayTracker::is_same_track(&current.state, state) && !replayed {
+                current.state = state.clone();
+                return None;
+            }
+        }
+
+        let finished = self.finish();
+
+        if state.playing.is_some() && state.playing != Some(PlaybackState::Stopped) {
+            self.current = Some(TrackedPlay {
+                state: state.clone(),
+                started_at: get_current_timestamp(),
+                listened: Duration::ZERO,
+                last_update: now,
+            });
+        }
+
+        finished
+    }
+
+    /// The track that's playing, if it was already listened to long enough to count.
+    /// It's still returned by `update` or `finish` once it ends
+    pub fn in_progress(&self) -> Option<Play> {
+        let current = self.current.as_ref()?;
+        let listened = current.listened_until(Instant::now());
+        self.threshold
+            .is_met(current.state.length, listened)
+            .then(|| current.to_play(listened))
+    }
+
+    /// Stop tracking the current track, returning it if it was listened to long enough
+    pub fn finish(&mut self) -> Option<Play> {
+        let current = self.current.take()?;
+        self.threshold
+            .is_met(current.state.length, current.listened)
+            .then(|| current.to_play(current.listened))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(title: &str, playing: PlaybackState, length_secs: u64) -> PlayerState {
+        PlayerState::new(
+            ":1.1".to_string(),
+            "mock".to_string(),
+            "Artist".to_string(),
+            "Album".to_string(),
+            title.to_string(),
+            Some(playing),
+            length_secs * 1000 * 1000,
+            0,
+        )
+    }
+
+    #[test]
+    fn playing_track_counts_once_past_the_threshold() {
+        let mut tracker = PlayTracker::new(50, Duration::ZERO);
+        assert!(tracker
+            .update(&state("One", PlaybackState::Playing, 180))
+            .is_none());
+
+        let play = tracker.in_progress().unwrap();
+        assert_eq!(play.title, "One");
+
+        // it's counted once, when it ends, and isn't in progress anymore after that
+        let finished = tracker.update(&state("Two", PlaybackState::Playing, 180));
+        assert_eq!(finished.unwrap().title, "One");
+        assert_eq!(tracker.in_progress().unwrap().title, "Two");
+    }
+
+    #[test]
+    fn playing_track_doesnt_count_before_the_threshold() {
+        let mut tracker = PlayTracker::new(50, Duration::from_secs(240));
+        tracker.update(&state("One", PlaybackState::Playing, 180));
+        assert!(tracker.in_progress().is_none());
+
+        tracker.update(&state("One", PlaybackState::Stopped, 180));
+        assert!(tracker.finish().is_none());
+        assert!(tracker.in_progress().is_none());
+    }
+}
@@ -1,7 +1,9 @@
 use std::time::Instant;
 
 use crate::{
-    models::{mpris_metadata::MprisMetadata, mpris_playback::MprisPlayback},
+    models::{
+        mpris_metadata::MprisMetadata, mpris_playback::MprisPlayback, playback_state::PlaybackState,
+    },
     utils::time::get_current_timestamp,
 };
 
@@ -61,6 +63,14 @@ impl PlayerClient {
             .unwrap_or(false)
     }
 
+    /// Players that never reported their playback state are considered stopped
+    pub fn is_stopped(&self) -> bool {
+        self.playback_state
+            .as_ref()
+            .and_then(|elem| elem.playing.clone())
+            .is_none_or(|playing| playing == PlaybackState::Stopped)
+    }
+
     pub fn update_metadata(&mut self, metadata: MprisMetadata) {
         self.metadata = metadata;
         self.last_updated = Instant::now();
@@ -0,0 +1,24 @@
+use serde::Serialize;
+
+/// A player on the bus as the module sees it, printed by the `players` subcommand
+#[derive(Debug, Serialize)]
+pub struct PlayerInfo {
+    /// Well-known name, like "org.mpris.MediaPlayer2.spotify"
+    pub bus_name: String,
+    /// Unique name of its connection, like ":1.42", which is what the module tracks
+    pub unique_name: String,
+    pub identity: String,
+    pub desktop_entry: String,
+    pub status: String,
+    pub artist: String,
+    pub title: String,
+    /// The `Can*` properties that are true, like "CanGoNext"
+    pub capabilities: Vec<String>,
+    /// Whether `--whitelist` lets the module handle it
+    pub whitelisted: bool,
+    /// Our own proxy, which mirrors one of the others and is never displayed
+    pub proxy: bool,
+    /// The `[icons.players]` key matching its identity, if the default icon isn't used
+    pub icon_rule: Option<String>,
+    pub icon: String,
+}
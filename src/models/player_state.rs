@@ -14,6 +14,11 @@ pub struct PlayerState {
     pub playing: Option<PlaybackState>,
     pub length: u64,
     pub position: u128,
+    pub art_url: String,
+    /// Location of the media, like a file path or a web page
+    pub url: String,
+    /// Whether the user has pinned this player, see `waybar-module-music pin`
+    pub pinned: bool,
 }
 
 impl PlayerState {
@@ -36,9 +41,23 @@ impl PlayerState {
             playing,
             length,
             position,
+            art_url: String::new(),
+            url: String::new(),
+            pinned: false,
         }
     }
 
+    pub fn with_pinned(mut self, pinned: bool) -> Self {
+        self.pinned = pinned;
+        self
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.playing
+            .as_ref()
+            .is_none_or(|playback| *playback == PlaybackState::Stopped)
+    }
+
     // if there's no artist or track, then we assume we're missing all data
     pub fn has_data(&self) -> bool {
         !self.artist.is_empty() && !self.title.is_empty()
@@ -57,7 +76,7 @@ impl PlayerState {
         let playing = playback.unwrap_or_default().playing;
         let length = metadata.length.unwrap_or(0);
 
-        Some(PlayerState::new(
+        let mut state = PlayerState::new(
             player_id,
             player_name,
             artist,
@@ -66,6 +85,10 @@ impl PlayerState {
             playing,
             length,
             position,
-        ))
+        );
+        state.art_url = metadata.art_url.unwrap_or_default();
+        state.url = metadata.url.unwrap_or_default();
+
+        Some(state)
     }
 }
@@ -0,0 +1,21 @@
+use bincode::{Decode, Encode};
+
+use crate::models::{lyrics::Lyrics, player_state::PlayerState};
+
+/// The result of looking up lyrics for a track
+#[derive(Debug, Clone, Encode, Decode, PartialEq)]
+pub struct TrackLyrics {
+    pub player_id: String,
+    pub artist: String,
+    pub title: String,
+    /// None when no lyrics were found
+    pub lyrics: Option<Lyrics>,
+}
+
+impl TrackLyrics {
+    pub fn is_for(&self, state: &PlayerState) -> bool {
+        self.player_id == state.player_id
+            && self.artist == state.artist
+            && self.title == state.title
+    }
+}
@@ -0,0 +1,77 @@
+use serde_json::json;
+
+use super::output::{escape_pango, Output, Update};
+
+/// The i3bar protocol, as read by i3bar and swaybar. Blocks use Pango markup,
+/// and clicks come back on stdin, see `ClickListener`
+pub struct I3bar;
+
+impl Output for I3bar {
+    fn header(&self) -> Option<String> {
+        // the body is an endless array, opened here and never closed
+        Some(format!(
+            "{}\n[",
+            json!({ "version": 1, "click_events": true })
+        ))
+    }
+
+    fn escape(&self, text: &str) -> String {
+        escape_pango(text)
+    }
+
+    fn format(&self, update: &Update) -> String {
+        let block = json!({
+            "name": "waybar-module-music",
+            // the instance has to stay the same, or i3 sees a new block on every status change
+            "instance": "music",
+            "full_text": update.text,
+            "markup": "pango",
+        });
+        format!("[{block}],")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use super::*;
+
+    #[test]
+    fn awkward_titles_still_make_valid_json() {
+        let text = I3bar.escape("Back\\slash \"quoted\"\nnext\tline\u{7}");
+        let line = I3bar.format(&Update {
+            text: &text,
+            classes: &[],
+            players: &[],
+        });
+
+        assert!(!line.contains('\n'));
+        // each update is an element of the endless array, followed by a comma
+        let blocks: Value = serde_json::from_str(line.strip_suffix(',').unwrap()).unwrap();
+        assert_eq!(
+            blocks[0]["full_text"],
+            "Back\\slash &quot;quoted&quot; next line"
+        );
+        assert_eq!(blocks[0]["markup"], "pango");
+    }
+
+    #[test]
+    fn instance_stays_the_same_whatever_plays() {
+        let playing = ["playing".to_string()];
+        let paused = ["paused".to_string()];
+        let instance = |classes: &[String]| {
+            let line = I3bar.format(&Update {
+                text: "Song",
+                classes,
+                players: &[],
+            });
+            let blocks: Value = serde_json::from_str(line.strip_suffix(',').unwrap()).unwrap();
+            blocks[0]["instance"].clone()
+        };
+
+        assert_eq!(instance(&playing), "music");
+        assert_eq!(instance(&paused), "music");
+        assert_eq!(instance(&[]), "music");
+    }
+}
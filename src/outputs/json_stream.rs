@@ -0,0 +1,22 @@
+use serde_json::json;
+
+use super::output::{strip_control_chars, Output, Update};
+
+/// A JSON object per update with the displayed players' full state,
+/// for widgets that lay things out themselves, like eww's `deflisten`
+pub struct JsonStream;
+
+impl Output for JsonStream {
+    fn escape(&self, text: &str) -> String {
+        strip_control_chars(text)
+    }
+
+    fn format(&self, update: &Update) -> String {
+        json!({
+            "text": update.text,
+            "class": update.classes,
+            "players": update.players,
+        })
+        .to_string()
+    }
+}
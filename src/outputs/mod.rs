@@ -0,0 +1,8 @@
+pub mod i3bar;
+pub mod json_stream;
+pub mod output;
+pub mod plain;
+pub mod polybar;
+pub mod preview;
+pub mod waybar;
+pub mod yambar;
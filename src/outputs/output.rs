@@ -0,0 +1,58 @@
+use crate::models::now_playing::NowPlaying;
+
+/// What the module shows right now, before it's written out for a particular bar
+pub struct Update<'a> {
+    /// The rendered format, with placeholder values already escaped by the output
+    pub text: &'a str,
+    /// Like `playing` or `pinned`, see the Styling section of the README
+    pub classes: &'a [String],
+    /// The displayed players, for outputs that pass on more than the text
+    pub players: &'a [NowPlaying],
+}
+
+/// Writes the module's state in the format a particular bar reads from its modules
+pub trait Output: Send + Sync {
+    /// Printed once, before the first update
+    fn header(&self) -> Option<String> {
+        None
+    }
+
+    /// Makes a placeholder value show up literally, rather than as the bar's markup
+    fn escape(&self, text: &str) -> String;
+
+    /// Makes the text around the placeholders of a format show up as written
+    fn escape_format(&self, text: &str) -> String {
+        self.escape(text)
+    }
+
+    /// A single update, printed as one line
+    fn format(&self, update: &Update) -> String;
+}
+
+/// Every output is line based, so a value must never start a new line.
+/// Titles from browser tabs often contain newlines and tabs, which become spaces,
+/// while other control characters are dropped
+pub fn strip_control_chars(text: &str) -> String {
+    text.chars()
+        .filter_map(|c| match c {
+            '\n' | '\r' | '\t' => Some(' '),
+            c if c.is_control() => None,
+            c => Some(c),
+        })
+        .collect()
+}
+
+pub fn escape_pango(text: &str) -> String {
+    strip_control_chars(text)
+        .chars()
+        .map(|x| match x {
+            // find and replace all special characters with escaped pango sequences
+            '&' => "&amp;".to_string(),
+            '<' => "&lt;".to_string(),
+            '>' => "&gt;".to_string(),
+            '\'' => "&#39;".to_string(),
+            '"' => "&quot;".to_string(),
+            _ => x.to_string(),
+        })
+        .collect()
+}
@@ -0,0 +1,14 @@
+use super::output::{strip_control_chars, Output, Update};
+
+/// Just the text, a line per update, for bars that take whatever a command prints
+pub struct Plain;
+
+impl Output for Plain {
+    fn escape(&self, text: &str) -> String {
+        strip_control_chars(text)
+    }
+
+    fn format(&self, update: &Update) -> String {
+        strip_control_chars(update.text)
+    }
+}
@@ -0,0 +1,48 @@
+use super::output::{strip_control_chars, Output, Update};
+
+/// Polybar's script module, where the format may use formatting tags like `%{F#1db954}`
+pub struct Polybar;
+
+impl Output for Polybar {
+    fn escape(&self, text: &str) -> String {
+        // a literal % is written as %%, which keeps values from opening tags of their own
+        strip_control_chars(text).replace('%', "%%")
+    }
+
+    /// The format is where Polybar's tags go, so it's left as it is
+    fn escape_format(&self, text: &str) -> String {
+        strip_control_chars(text)
+    }
+
+    fn format(&self, update: &Update) -> String {
+        strip_control_chars(update.text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percent_signs_in_values_cant_open_tags() {
+        assert_eq!(Polybar.escape("100%{F#f00} Hits\n"), "100%%{F#f00} Hits ");
+    }
+
+    #[test]
+    fn tags_in_the_format_are_kept() {
+        assert_eq!(
+            Polybar.escape_format("%{F#1db954}\u{7}%{F-}"),
+            "%{F#1db954}%{F-}"
+        );
+    }
+
+    #[test]
+    fn updates_stay_on_one_line() {
+        let line = Polybar.format(&Update {
+            text: "Artist\nTitle",
+            classes: &[],
+            players: &[],
+        });
+        assert_eq!(line, "Artist Title");
+    }
+}
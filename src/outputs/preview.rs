@@ -0,0 +1,50 @@
+use unicode_segmentation::UnicodeSegmentation;
+
+use super::{
+    output::{escape_pango, Output, Update},
+    waybar::Waybar,
+};
+
+/// Redraws the terminal with the text as it would look in Waybar, its width and the JSON Waybar
+/// would be sent, for `waybar-module-music preview`. Unlike the other outputs, an update takes
+/// several lines
+pub struct Preview;
+
+/// The text without its Pango markup, as Waybar shows it
+fn visible_text(markup: &str) -> String {
+    let mut text = String::new();
+    let mut in_tag = false;
+    for c in markup.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' => in_tag = false,
+            // the invisible separators around the artist and title
+            '\u{2063}' => (),
+            c if !in_tag => text.push(c),
+            _ => (),
+        }
+    }
+
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&#39;", "'")
+        .replace("&quot;", "\"")
+        .replace("&amp;", "&")
+}
+
+impl Output for Preview {
+    fn escape(&self, text: &str) -> String {
+        escape_pango(text)
+    }
+
+    fn format(&self, update: &Update) -> String {
+        let text = visible_text(update.text);
+        format!(
+            // move to the top left and clear the screen, so each update replaces the last
+            "\x1b[H\x1b[J{text}\n\nwidth: {} characters\nclasses: {}\n{}",
+            text.graphemes(true).count(),
+            update.classes.join(" "),
+            Waybar.format(update)
+        )
+    }
+}
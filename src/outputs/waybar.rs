@@ -0,0 +1,71 @@
+use serde::Serialize;
+
+use super::output::{escape_pango, Output, Update};
+
+/// Waybar's custom module JSON, with the text as Pango markup
+pub struct Waybar;
+
+/// A line of `return-type: json` output, see waybar-custom(5)
+#[derive(Debug, Serialize)]
+struct WaybarOutput<'a> {
+    text: &'a str,
+    tooltip: &'a str,
+    class: &'a [String],
+    alt: &'a str,
+    /// How far into the track the displayed player is, for `format-icons`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    percentage: Option<u8>,
+}
+
+impl Output for Waybar {
+    fn escape(&self, text: &str) -> String {
+        escape_pango(text)
+    }
+
+    fn format(&self, update: &Update) -> String {
+        let percentage = update
+            .players
+            .first()
+            .filter(|player| player.length > 0)
+            .map(|player| (player.position * 100 / player.length).min(100) as u8);
+
+        let output = WaybarOutput {
+            text: update.text,
+            tooltip: "",
+            class: update.classes,
+            alt: "",
+            percentage,
+        };
+
+        // serializing plain strings can't fail, but Waybar should never be left with a broken line
+        serde_json::to_string(&output).unwrap_or_else(|_| String::from("{}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use super::*;
+
+    #[test]
+    fn awkward_titles_still_make_valid_json() {
+        let text = Waybar.escape("Back\\slash \"quoted\"\nnext\tline\u{7}");
+        let classes = [String::from("playing")];
+        let line = Waybar.format(&Update {
+            text: &text,
+            classes: &classes,
+            players: &[],
+        });
+
+        assert!(!line.contains('\n'));
+        let output: Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(output["text"], "Back\\slash &quot;quoted&quot; next line");
+        assert_eq!(output["class"][0], "playing");
+    }
+
+    #[test]
+    fn control_chars_go_before_pango_escaping() {
+        assert_eq!(Waybar.escape("R&\u{1b}B\r<"), "R&amp;B &lt;");
+    }
+}
@@ -0,0 +1,54 @@
+use super::output::{strip_control_chars, Output, Update};
+
+/// yambar's script module, as a transaction of `name|type|value` tags ended by an empty line
+pub struct Yambar;
+
+impl Output for Yambar {
+    fn escape(&self, text: &str) -> String {
+        strip_control_chars(text)
+    }
+
+    fn format(&self, update: &Update) -> String {
+        let status = update
+            .classes
+            .first()
+            .map(String::as_str)
+            .unwrap_or("stopped");
+        format!(
+            "text|string|{}\nstatus|string|{status}\nclasses|string|{}\n",
+            strip_control_chars(update.text),
+            update.classes.join(" ")
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn newlines_in_the_text_cant_end_the_transaction() {
+        let classes = ["playing".to_string(), "pinned".to_string()];
+        let text = Yambar.escape("Title\n\nstatus|string|paused");
+        let transaction = Yambar.format(&Update {
+            text: &text,
+            classes: &classes,
+            players: &[],
+        });
+
+        assert_eq!(
+            transaction,
+            "text|string|Title  status|string|paused\nstatus|string|playing\nclasses|string|playing pinned\n"
+        );
+    }
+
+    #[test]
+    fn status_is_stopped_without_classes() {
+        let transaction = Yambar.format(&Update {
+            text: "",
+            classes: &[],
+            players: &[],
+        });
+        assert!(transaction.contains("\nstatus|string|stopped\n"));
+    }
+}
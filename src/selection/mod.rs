@@ -0,0 +1,56 @@
+pub mod most_recent;
+pub mod policy;
+pub mod prefer_playing;
+pub mod priority;
+pub mod sticky;
+
+use crate::models::args::{Args, SelectionMode};
+
+use self::{
+    most_recent::MostRecent, policy::SelectionPolicy, prefer_playing::PreferPlaying,
+    priority::Priority, sticky::StickyUntilStopped,
+};
+
+/// The policy chosen with `--selection`
+pub fn from_args(args: &Args) -> Box<dyn SelectionPolicy> {
+    match args.selection {
+        SelectionMode::MostRecent => Box::new(MostRecent),
+        SelectionMode::Priority => Box::new(Priority::new(args.priority.clone())),
+        SelectionMode::PreferPlaying => Box::new(PreferPlaying),
+        SelectionMode::StickyUntilStopped => Box::new(StickyUntilStopped),
+    }
+}
+
+/// Players built by hand, so the policies can be tested without D-Bus
+#[cfg(test)]
+pub mod testing {
+    use std::{
+        collections::HashMap,
+        time::{Duration, Instant},
+    };
+
+    use crate::models::{
+        mpris_metadata::MprisMetadata, mpris_playback::MprisPlayback,
+        playback_state::PlaybackState, player_client::PlayerClient,
+    };
+
+    /// A player named `name` with the ID ":1.<name>", last updated `updated` seconds after `start`
+    pub fn player(
+        start: Instant,
+        name: &str,
+        playing: Option<PlaybackState>,
+        updated: u64,
+    ) -> PlayerClient {
+        let id = format!(":1.{name}");
+        let mut player = PlayerClient::new(name.to_string(), MprisMetadata::new(id.clone()));
+        if playing.is_some() {
+            player.update_playback_state(MprisPlayback::new_with_playing(id, playing));
+        }
+        player.last_updated = start + Duration::from_secs(updated);
+        player
+    }
+
+    pub fn players(players: Vec<PlayerClient>) -> HashMap<String, PlayerClient> {
+        players.into_iter().map(|p| (p.get_id(), p)).collect()
+    }
+}
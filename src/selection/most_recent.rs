@@ -0,0 +1,62 @@
+use std::{cmp::Reverse, collections::HashMap};
+
+use crate::models::player_client::PlayerClient;
+
+use super::policy::SelectionPolicy;
+
+/// Always display whichever player was updated last, regardless of playback state
+pub struct MostRecent;
+
+impl SelectionPolicy for MostRecent {
+    fn rank(&self, players: &HashMap<String, PlayerClient>, _: Option<&str>) -> Vec<String> {
+        let mut players = players.values().collect::<Vec<&PlayerClient>>();
+        players.sort_by_key(|p| Reverse(p.last_updated));
+        players.into_iter().map(|p| p.get_id()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use crate::{
+        models::playback_state::PlaybackState,
+        selection::testing::{player, players},
+    };
+
+    use super::*;
+
+    #[test]
+    fn newest_update_wins_regardless_of_playback() {
+        let start = Instant::now();
+        let players = players(vec![
+            player(start, "spotify", Some(PlaybackState::Playing), 1),
+            player(start, "firefox", Some(PlaybackState::Paused), 2),
+            player(start, "mpv", Some(PlaybackState::Stopped), 0),
+        ]);
+
+        assert_eq!(
+            MostRecent.rank(&players, None),
+            vec![":1.firefox", ":1.spotify", ":1.mpv"]
+        );
+    }
+
+    #[test]
+    fn current_player_makes_no_difference() {
+        let start = Instant::now();
+        let players = players(vec![
+            player(start, "spotify", Some(PlaybackState::Playing), 1),
+            player(start, "firefox", Some(PlaybackState::Playing), 2),
+        ]);
+
+        assert_eq!(
+            MostRecent.select(&players, Some(":1.spotify")),
+            Some(String::from(":1.firefox"))
+        );
+    }
+
+    #[test]
+    fn nothing_to_select_without_players() {
+        assert_eq!(MostRecent.select(&HashMap::new(), None), None);
+    }
+}
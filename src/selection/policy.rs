@@ -0,0 +1,18 @@
+use std::collections::HashMap;
+
+use crate::models::player_client::PlayerClient;
+
+/// Decides which player should be displayed when several are known
+pub trait SelectionPolicy: Send + Sync {
+    /// Player IDs ordered from most to least preferred.
+    /// `current` is the ID of the player displayed right now, if any
+    fn rank(&self, players: &HashMap<String, PlayerClient>, current: Option<&str>) -> Vec<String>;
+
+    fn select(
+        &self,
+        players: &HashMap<String, PlayerClient>,
+        current: Option<&str>,
+    ) -> Option<String> {
+        self.rank(players, current).into_iter().next()
+    }
+}
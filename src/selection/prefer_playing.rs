@@ -0,0 +1,74 @@
+use std::{cmp::Reverse, collections::HashMap};
+
+use crate::models::player_client::PlayerClient;
+
+use super::policy::SelectionPolicy;
+
+/// Display the most recently updated player that is playing.
+/// If nothing is playing, fall back to the most recently paused player, then stopped players
+pub struct PreferPlaying;
+
+impl SelectionPolicy for PreferPlaying {
+    fn rank(&self, players: &HashMap<String, PlayerClient>, _: Option<&str>) -> Vec<String> {
+        let mut players = players.values().collect::<Vec<&PlayerClient>>();
+        players.sort_by_key(|p| (!p.playing(), p.is_stopped(), Reverse(p.last_updated)));
+        players.into_iter().map(|p| p.get_id()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use crate::{
+        models::playback_state::PlaybackState,
+        selection::testing::{player, players},
+    };
+
+    use super::*;
+
+    #[test]
+    fn playing_wins_over_more_recently_paused() {
+        let start = Instant::now();
+        let players = players(vec![
+            player(start, "spotify", Some(PlaybackState::Playing), 1),
+            player(start, "firefox", Some(PlaybackState::Paused), 5),
+        ]);
+
+        assert_eq!(
+            PreferPlaying.select(&players, None),
+            Some(String::from(":1.spotify"))
+        );
+    }
+
+    #[test]
+    fn newest_update_breaks_ties_between_playing_players() {
+        let start = Instant::now();
+        let players = players(vec![
+            player(start, "spotify", Some(PlaybackState::Playing), 1),
+            player(start, "firefox", Some(PlaybackState::Playing), 2),
+            player(start, "mpv", Some(PlaybackState::Playing), 0),
+        ]);
+
+        assert_eq!(
+            PreferPlaying.rank(&players, None),
+            vec![":1.firefox", ":1.spotify", ":1.mpv"]
+        );
+    }
+
+    #[test]
+    fn paused_before_stopped_and_unknown() {
+        let start = Instant::now();
+        let players = players(vec![
+            player(start, "spotify", Some(PlaybackState::Stopped), 3),
+            player(start, "firefox", Some(PlaybackState::Paused), 1),
+            player(start, "mpv", None, 2),
+            player(start, "vlc", Some(PlaybackState::Paused), 2),
+        ]);
+
+        let ranked = PreferPlaying.rank(&players, None);
+        assert_eq!(ranked[..2], [":1.vlc", ":1.firefox"]);
+        // players that never reported their state count as stopped, newest first
+        assert_eq!(ranked[2..], [":1.spotify", ":1.mpv"]);
+    }
+}
@@ -0,0 +1,145 @@
+use std::{cmp::Reverse, collections::HashMap};
+
+use crate::models::player_client::PlayerClient;
+
+use super::policy::SelectionPolicy;
+
+/// Display players in a fixed order of preference, e.g "spotify mpv firefox", even if a less
+/// preferred player is playing. Unlisted players come after listed ones, and stopped players
+/// come last, so a player that's merely open doesn't hide one that's playing
+pub struct Priority {
+    priorities: Vec<String>,
+}
+
+impl Priority {
+    pub fn new(priorities: Vec<String>) -> Self {
+        Self {
+            priorities: priorities.iter().map(|p| p.to_lowercase()).collect(),
+        }
+    }
+
+    /// Index of the first entry partially matching the player's name
+    fn priority_of(&self, player: &PlayerClient) -> usize {
+        let name = player.name().to_lowercase();
+        self.priorities
+            .iter()
+            .position(|p| name.contains(p))
+            .unwrap_or(self.priorities.len())
+    }
+}
+
+impl SelectionPolicy for Priority {
+    fn rank(&self, players: &HashMap<String, PlayerClient>, _: Option<&str>) -> Vec<String> {
+        let mut players = players.values().collect::<Vec<&PlayerClient>>();
+        players.sort_by_key(|p| {
+            (
+                p.is_stopped(),
+                self.priority_of(p),
+                !p.playing(),
+                Reverse(p.last_updated),
+            )
+        });
+        players.into_iter().map(|p| p.get_id()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use crate::{
+        models::playback_state::PlaybackState,
+        selection::testing::{player, players},
+    };
+
+    use super::*;
+
+    fn priority(order: &[&str]) -> Priority {
+        Priority::new(order.iter().map(|p| p.to_string()).collect())
+    }
+
+    #[test]
+    fn preferred_player_wins_even_when_paused() {
+        let start = Instant::now();
+        let players = players(vec![
+            player(start, "Spotify", Some(PlaybackState::Paused), 0),
+            player(start, "Mozilla Firefox", Some(PlaybackState::Playing), 5),
+        ]);
+
+        assert_eq!(
+            priority(&["spotify", "firefox"]).rank(&players, None),
+            vec![":1.Spotify", ":1.Mozilla Firefox"]
+        );
+    }
+
+    #[test]
+    fn unknown_players_come_after_listed_ones() {
+        let start = Instant::now();
+        let players = players(vec![
+            player(start, "vlc", Some(PlaybackState::Playing), 5),
+            player(start, "mpv", Some(PlaybackState::Paused), 0),
+        ]);
+
+        assert_eq!(
+            priority(&["spotify", "mpv"]).select(&players, None),
+            Some(String::from(":1.mpv"))
+        );
+    }
+
+    #[test]
+    fn unknown_players_fall_back_to_playing_then_newest() {
+        let start = Instant::now();
+        let players = players(vec![
+            player(start, "vlc", Some(PlaybackState::Paused), 5),
+            player(start, "mpv", Some(PlaybackState::Playing), 0),
+            player(start, "celluloid", Some(PlaybackState::Playing), 1),
+        ]);
+
+        assert_eq!(
+            priority(&["spotify"]).rank(&players, None),
+            vec![":1.celluloid", ":1.mpv", ":1.vlc"]
+        );
+    }
+
+    #[test]
+    fn missing_priorities_rank_like_prefer_playing() {
+        let start = Instant::now();
+        let players = players(vec![
+            player(start, "spotify", Some(PlaybackState::Paused), 5),
+            player(start, "mpv", Some(PlaybackState::Playing), 0),
+        ]);
+
+        assert_eq!(
+            priority(&[]).select(&players, None),
+            Some(String::from(":1.mpv"))
+        );
+    }
+
+    #[test]
+    fn stopped_players_come_last_whatever_their_priority() {
+        let start = Instant::now();
+        let players = players(vec![
+            player(start, "spotify", Some(PlaybackState::Stopped), 5),
+            player(start, "firefox", Some(PlaybackState::Paused), 0),
+        ]);
+
+        assert_eq!(
+            priority(&["spotify", "firefox"]).select(&players, None),
+            Some(String::from(":1.firefox"))
+        );
+    }
+
+    #[test]
+    fn entries_match_partially_and_ignore_case() {
+        let start = Instant::now();
+        let players = players(vec![
+            player(start, "Mozilla Firefox", Some(PlaybackState::Playing), 0),
+            player(start, "mpv", Some(PlaybackState::Playing), 5),
+        ]);
+
+        assert_eq!(
+            priority(&["FIREFOX"]).select(&players, None),
+            Some(String::from(":1.Mozilla Firefox"))
+        );
+    }
+}
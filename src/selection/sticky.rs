@@ -0,0 +1,93 @@
+use std::collections::HashMap;
+
+use crate::models::player_client::PlayerClient;
+
+use super::{policy::SelectionPolicy, prefer_playing::PreferPlaying};
+
+/// Keep displaying the current player until it stops, even if another player starts playing.
+/// Once it stops, the next player is chosen like `PreferPlaying` would
+pub struct StickyUntilStopped;
+
+impl SelectionPolicy for StickyUntilStopped {
+    fn rank(&self, players: &HashMap<String, PlayerClient>, current: Option<&str>) -> Vec<String> {
+        let mut ranked = PreferPlaying.rank(players, current);
+
+        if let Some(current) =
+            current.filter(|id| players.get(*id).is_some_and(|p| !p.is_stopped()))
+        {
+            ranked.retain(|id| id != current);
+            ranked.insert(0, current.to_string());
+        }
+
+        ranked
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use crate::{
+        models::playback_state::PlaybackState,
+        selection::testing::{player, players},
+    };
+
+    use super::*;
+
+    #[test]
+    fn current_player_stays_while_another_starts_playing() {
+        let start = Instant::now();
+        let players = players(vec![
+            player(start, "spotify", Some(PlaybackState::Paused), 0),
+            player(start, "firefox", Some(PlaybackState::Playing), 5),
+        ]);
+
+        assert_eq!(
+            StickyUntilStopped.select(&players, Some(":1.spotify")),
+            Some(String::from(":1.spotify"))
+        );
+    }
+
+    #[test]
+    fn stopped_current_player_is_replaced() {
+        let start = Instant::now();
+        let players = players(vec![
+            player(start, "spotify", Some(PlaybackState::Stopped), 5),
+            player(start, "firefox", Some(PlaybackState::Paused), 0),
+        ]);
+
+        assert_eq!(
+            StickyUntilStopped.select(&players, Some(":1.spotify")),
+            Some(String::from(":1.firefox"))
+        );
+    }
+
+    #[test]
+    fn gone_current_player_is_replaced() {
+        let start = Instant::now();
+        let players = players(vec![
+            player(start, "firefox", Some(PlaybackState::Paused), 0),
+            player(start, "mpv", Some(PlaybackState::Playing), 1),
+        ]);
+
+        assert_eq!(
+            StickyUntilStopped.select(&players, Some(":1.spotify")),
+            Some(String::from(":1.mpv"))
+        );
+    }
+
+    #[test]
+    fn without_current_player_ranks_like_prefer_playing() {
+        let start = Instant::now();
+        let players = players(vec![
+            player(start, "spotify", Some(PlaybackState::Playing), 1),
+            player(start, "firefox", Some(PlaybackState::Playing), 2),
+            player(start, "mpv", Some(PlaybackState::Paused), 3),
+        ]);
+
+        assert_eq!(
+            StickyUntilStopped.rank(&players, None),
+            vec![":1.firefox", ":1.spotify", ":1.mpv"]
+        );
+    }
+}
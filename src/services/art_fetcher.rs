@@ -0,0 +1,127 @@
+use std::{
+    sync::{mpsc::Receiver, Arc},
+    thread::{self, JoinHandle},
+};
+
+use bincode::config;
+use log::{error, info, warn};
+
+use crate::{
+    event_bus::{EventBusHandle, EventType},
+    interfaces::art_cache::ArtCache,
+    models::{cached_art::CachedArt, config::Config, player_state::PlayerState},
+    utils::color,
+};
+
+use super::runnable::Runnable;
+
+/// Copies the displayed player's album art into the local cache and finds its dominant color,
+/// off the display thread, then publishes the result
+pub struct ArtFetcher {
+    config: Arc<Config>,
+    event_bus: EventBusHandle,
+}
+
+impl ArtFetcher {
+    pub fn new(config: Arc<Config>, event_bus: EventBusHandle) -> Self {
+        Self { config, event_bus }
+    }
+
+    fn init_worker(&self) {
+        let cache = match ArtCache::new(self.config.art_cache().max_size_mb * 1024 * 1024) {
+            Ok(cache) => cache,
+            Err(err) => {
+                error!("failed to open the art cache: {err}");
+                return;
+            }
+        };
+
+        match self.event_bus.subscribe(EventType::PlayerStateChanged) {
+            Some(rx) => self.listen_for_updates(rx, cache),
+            None => error!("failed to subscribe to PlayerStateChanged listener"),
+        }
+    }
+
+    fn decode(encoded: &[u8]) -> Option<PlayerState> {
+        match bincode::decode_from_slice::<PlayerState, _>(encoded, config::standard()) {
+            Ok((state, _)) => Some(state),
+            Err(err) => {
+                warn!("failed to decode message in ArtFetcher: {err}");
+                None
+            }
+        }
+    }
+
+    fn listen_for_updates(&self, rx: Receiver<Vec<u8>>, cache: ArtCache) {
+        let mut current_url: Option<String> = None;
+
+        while let Ok(encoded) = rx.recv() {
+            // fetching can take a while, by then only the newest state matters
+            let Some(state) = std::iter::once(encoded)
+                .chain(rx.try_iter())
+                .filter_map(|encoded| ArtFetcher::decode(&encoded))
+                .last()
+            else {
+                continue;
+            };
+
+            if current_url.as_ref() == Some(&state.art_url) {
+                continue;
+            }
+            current_url = Some(state.art_url.clone());
+
+            if state.art_url.is_empty() {
+                if let Err(err) = cache.set_current(None) {
+                    warn!("failed to remove the current art link: {err}");
+                }
+                continue;
+            }
+
+            match cache.resolve(&state.art_url) {
+                Ok(path) => {
+                    if let Err(err) = cache.set_current(Some(&path)) {
+                        warn!("failed to update the current art link: {err}");
+                    }
+                    let color = match color::dominant_color(&path) {
+                        Ok(color) => Some(color),
+                        Err(err) => {
+                            warn!("failed to get the color of '{}': {err}", state.art_url);
+                            None
+                        }
+                    };
+
+                    self.publish(CachedArt {
+                        art_url: state.art_url,
+                        path: path.to_string_lossy().into_owned(),
+                        color,
+                    });
+                }
+                Err(err) => {
+                    warn!("failed to cache art from '{}': {err}", state.art_url);
+                    if let Err(err) = cache.set_current(None) {
+                        warn!("failed to remove the current art link: {err}");
+                    }
+                }
+            }
+        }
+
+        warn!("ArtFetcher lost its PlayerStateChanged subscription");
+    }
+
+    fn publish(&self, art: CachedArt) {
+        match bincode::encode_to_vec(art, config::standard()) {
+            Ok(encoded) => self.event_bus.publish(EventType::ArtCached, encoded),
+            Err(err) => error!("failed to encode cached art: {err}"),
+        }
+    }
+}
+
+impl Runnable for ArtFetcher {
+    fn run(self: Arc<Self>) -> JoinHandle<()> {
+        thread::spawn(move || {
+            info!("starting ArtFetcher thread");
+            self.init_worker();
+            info!("ArtFetcher thread is stopping");
+        })
+    }
+}
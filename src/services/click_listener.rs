@@ -0,0 +1,74 @@
+use std::{
+    io::{self, BufRead},
+    sync::Arc,
+    thread::{self, JoinHandle},
+};
+
+use bincode::config;
+use log::{debug, error, info, warn};
+
+use crate::{
+    event_bus::{EventBusHandle, EventType},
+    models::click_event::ClickEvent,
+};
+
+use super::runnable::Runnable;
+
+/// Turns i3bar click events from stdin into control commands, see `--output i3bar`
+pub struct ClickListener {
+    event_bus: EventBusHandle,
+}
+
+impl ClickListener {
+    pub fn new(event_bus: EventBusHandle) -> Self {
+        Self { event_bus }
+    }
+
+    fn listen(&self) {
+        for line in io::stdin().lock().lines() {
+            let line = match line {
+                Ok(line) => line,
+                Err(err) => {
+                    warn!("failed to read click event: {err}");
+                    return;
+                }
+            };
+
+            // the events are an endless JSON array, one element per line
+            let event = line.trim().trim_start_matches(['[', ',']);
+            if event.is_empty() {
+                continue;
+            }
+
+            let command = match serde_json::from_str::<ClickEvent>(event) {
+                Ok(click) => match click.command() {
+                    Some(command) => command,
+                    None => {
+                        debug!("nothing to do for button {}", click.button);
+                        continue;
+                    }
+                },
+                Err(err) => {
+                    warn!("got invalid click event, skipping: {err}");
+                    continue;
+                }
+            };
+
+            info!("received click for '{command}'");
+            match bincode::encode_to_vec(&command, config::standard()) {
+                Ok(encoded) => self.event_bus.publish(EventType::ControlCommand, encoded),
+                Err(err) => error!("failed to encode control command: {err}"),
+            }
+        }
+    }
+}
+
+impl Runnable for ClickListener {
+    fn run(self: Arc<Self>) -> JoinHandle<()> {
+        thread::spawn(move || {
+            info!("starting ClickListener thread");
+            self.listen();
+            info!("ClickListener thread is stopping");
+        })
+    }
+}
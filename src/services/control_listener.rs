@@ -0,0 +1,71 @@
+use std::{
+    io::Read,
+    sync::Arc,
+    thread::{self, JoinHandle},
+};
+
+use bincode::config;
+use log::{error, info, warn};
+
+use crate::{
+    event_bus::{EventBusHandle, EventType},
+    interfaces::control_socket,
+    models::control_command::ControlCommand,
+};
+
+use super::runnable::Runnable;
+
+/// Listens for commands from other processes, like `waybar-module-music pin`,
+/// and publishes them on the event bus
+pub struct ControlListener {
+    event_bus: EventBusHandle,
+}
+
+impl ControlListener {
+    pub fn new(event_bus: EventBusHandle) -> Self {
+        Self { event_bus }
+    }
+
+    fn listen(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let listener = control_socket::bind()?;
+
+        for stream in listener.incoming() {
+            let mut stream = match stream {
+                Ok(stream) => stream,
+                Err(err) => {
+                    warn!("failed to accept control connection: {err}");
+                    continue;
+                }
+            };
+
+            let mut encoded = vec![];
+            if let Err(err) = stream.read_to_end(&mut encoded) {
+                warn!("failed to read control command: {err}");
+                continue;
+            }
+
+            match bincode::decode_from_slice::<ControlCommand, _>(&encoded[..], config::standard())
+            {
+                Ok((command, _)) => {
+                    info!("received control command '{command}'");
+                    self.event_bus.publish(EventType::ControlCommand, encoded);
+                }
+                Err(err) => warn!("got invalid control command, skipping: {err}"),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Runnable for ControlListener {
+    fn run(self: Arc<Self>) -> JoinHandle<()> {
+        thread::spawn(move || {
+            info!("starting ControlListener thread");
+            if let Err(err) = self.listen() {
+                error!("ControlListener failed: {err}");
+            }
+            info!("ControlListener thread is stopping");
+        })
+    }
+}
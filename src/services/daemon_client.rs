@@ -0,0 +1,165 @@
+use std::{
+    io::BufReader,
+    os::unix::net::UnixStream,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::Receiver,
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use bincode::config;
+use log::{error, info, warn};
+
+use crate::{
+    event_bus::{EventBusHandle, EventType},
+    interfaces::daemon_socket::{self, Frame},
+    models::bus_status::BusStatus,
+};
+
+use super::runnable::Runnable;
+
+/// How long the daemon gets to come back, before we look for the players ourselves
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(2);
+
+type Fallback = Box<dyn Fn() -> Vec<Arc<dyn Runnable>> + Send + Sync>;
+
+/// Publishes what the daemon sends us on our own bus, as if our own services had,
+/// and sends our control commands, like clicks, to the daemon
+pub struct DaemonClient {
+    event_bus: EventBusHandle,
+    connection: Mutex<Option<BufReader<UnixStream>>>,
+    /// Where our commands go, for as long as we're connected
+    commands: Mutex<Option<UnixStream>>,
+    /// The services we'd have run without a daemon
+    fallback: Fallback,
+    on_our_own: AtomicBool,
+}
+
+impl DaemonClient {
+    pub fn new(
+        event_bus: EventBusHandle,
+        connection: BufReader<UnixStream>,
+        fallback: Fallback,
+    ) -> Self {
+        Self {
+            event_bus,
+            connection: Mutex::new(Some(connection)),
+            commands: Mutex::new(None),
+            fallback,
+            on_our_own: AtomicBool::new(false),
+        }
+    }
+
+    fn send_commands(&self, rx: Receiver<Vec<u8>>) {
+        for data in rx {
+            let Ok(mut commands) = self.commands.lock() else {
+                return;
+            };
+
+            let frame = Frame {
+                event_type: EventType::ControlCommand,
+                data,
+            };
+            match commands.as_mut() {
+                // our own PlayerManager handles them now
+                None if self.on_our_own.load(Ordering::Relaxed) => return,
+                Some(stream) => {
+                    if let Err(err) = daemon_socket::write_frame(stream, &frame) {
+                        warn!("failed to send control command to the daemon: {err}");
+                    }
+                }
+                None => warn!("not connected to the daemon, dropping control command"),
+            }
+        }
+    }
+
+    fn set_connection(&self, connection: Option<&BufReader<UnixStream>>) {
+        if let Ok(mut commands) = self.commands.lock() {
+            *commands = connection.and_then(|c| c.get_ref().try_clone().ok());
+        }
+    }
+
+    fn forward_frames(&self, connection: &mut BufReader<UnixStream>) {
+        loop {
+            match daemon_socket::read_frame(connection) {
+                // our Display may not have subscribed yet when the daemon catches us up
+                Ok(frame) => self
+                    .event_bus
+                    .publish_retained(frame.event_type, frame.data),
+                Err(err) => {
+                    warn!("lost connection to the daemon: {err}");
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Shows the error label while the daemon is away. If it isn't back soon, we run on our own,
+    /// as if there had been no daemon to begin with
+    fn receive(&self) {
+        let Some(mut connection) = self.connection.lock().ok().and_then(|mut c| c.take()) else {
+            return;
+        };
+
+        loop {
+            self.set_connection(Some(&connection));
+            self.forward_frames(&mut connection);
+            self.set_connection(None);
+            self.publish_bus_status(BusStatus::Disconnected(String::from("the daemon stopped")));
+
+            thread::sleep(RECONNECT_INTERVAL);
+            connection = match daemon_socket::connect() {
+                Ok(connection) => {
+                    info!("reconnected to the daemon");
+                    connection
+                }
+                Err(err) => {
+                    info!("the daemon is gone, running on our own: {err}");
+                    self.run_on_our_own();
+                    return;
+                }
+            };
+        }
+    }
+
+    fn publish_bus_status(&self, status: BusStatus) {
+        match bincode::encode_to_vec(status, config::standard()) {
+            Ok(encoded) => self
+                .event_bus
+                .publish_retained(EventType::BusStatusChanged, encoded),
+            Err(err) => warn!("failed to encode bus status: {err}"),
+        }
+    }
+
+    fn run_on_our_own(&self) {
+        self.on_our_own.store(true, Ordering::Relaxed);
+
+        let handles: Vec<JoinHandle<()>> = (self.fallback)()
+            .into_iter()
+            .map(|service| service.run())
+            .collect();
+        for handle in handles {
+            let _ = handle.join();
+        }
+    }
+}
+
+impl Runnable for DaemonClient {
+    fn run(self: Arc<Self>) -> JoinHandle<()> {
+        thread::spawn(move || {
+            info!("starting DaemonClient thread");
+            match self.event_bus.subscribe(EventType::ControlCommand) {
+                Some(rx) => {
+                    let client = self.clone();
+                    thread::spawn(move || client.send_commands(rx));
+                }
+                None => error!("failed to subscribe to ControlCommand listener"),
+            }
+            self.receive();
+            info!("DaemonClient thread is stopping");
+        })
+    }
+}
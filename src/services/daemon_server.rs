@@ -0,0 +1,176 @@
+use std::{
+    collections::HashMap,
+    io::BufReader,
+    os::unix::net::{UnixListener, UnixStream},
+    sync::{
+        mpsc::{self, Sender},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use log::{debug, error, info, warn};
+
+use crate::{
+    event_bus::{EventBusHandle, EventType},
+    interfaces::daemon_socket::{self, Frame},
+};
+
+use super::runnable::Runnable;
+
+/// Broadcasts what the bars need to render to every client connected to the daemon
+pub struct DaemonServer {
+    event_bus: EventBusHandle,
+    listener: UnixListener,
+    clients: Mutex<Clients>,
+}
+
+#[derive(Default)]
+struct Clients {
+    streams: Vec<UnixStream>,
+    /// The latest event of each type, so clients that connect later start out up to date
+    latest: HashMap<EventType, Vec<u8>>,
+}
+
+impl DaemonServer {
+    pub fn new(event_bus: EventBusHandle, listener: UnixListener) -> Self {
+        Self {
+            event_bus,
+            listener,
+            clients: Mutex::new(Clients::default()),
+        }
+    }
+
+    fn forward_event(&self, event_type: EventType, tx: Sender<Frame>) {
+        match self.event_bus.subscribe(event_type.clone()) {
+            Some(rx) => {
+                thread::spawn(move || {
+                    for data in rx {
+                        let frame = Frame {
+                            event_type: event_type.clone(),
+                            data,
+                        };
+                        if tx.send(frame).is_err() {
+                            return;
+                        }
+                    }
+                });
+            }
+            None => error!("failed to subscribe to {event_type} listener"),
+        }
+    }
+
+    fn broadcast(&self, frame: Frame) {
+        let Ok(mut clients) = self.clients.lock() else {
+            return;
+        };
+
+        clients
+            .streams
+            .retain_mut(|stream| match daemon_socket::write_frame(stream, &frame) {
+                Ok(()) => true,
+                Err(err) => {
+                    debug!("dropping daemon client: {err}");
+                    false
+                }
+            });
+        clients.latest.insert(frame.event_type, frame.data);
+    }
+
+    fn welcome(&self, mut stream: UnixStream) {
+        // a client that stops reading shouldn't hold up everyone else
+        if let Err(err) = stream.set_write_timeout(Some(Duration::from_secs(1))) {
+            warn!("failed to set up daemon client: {err}");
+            return;
+        }
+
+        let Ok(mut clients) = self.clients.lock() else {
+            return;
+        };
+
+        let mut greeting = daemon_socket::write_hello(&mut stream);
+        for (event_type, data) in &clients.latest {
+            if greeting.is_err() {
+                break;
+            }
+            let frame = Frame {
+                event_type: event_type.clone(),
+                data: data.clone(),
+            };
+            greeting = daemon_socket::write_frame(&mut stream, &frame);
+        }
+
+        let commands = match stream.try_clone() {
+            Ok(commands) => commands,
+            Err(err) => {
+                warn!("failed to set up daemon client: {err}");
+                return;
+            }
+        };
+
+        match greeting {
+            Ok(()) => {
+                info!("daemon client connected");
+                clients.streams.push(stream);
+                let event_bus = self.event_bus.clone();
+                thread::spawn(move || DaemonServer::receive_commands(commands, event_bus));
+            }
+            Err(err) => warn!("failed to greet daemon client: {err}"),
+        }
+    }
+
+    /// Clients send us their control commands, like clicks on a bar that reads them itself
+    fn receive_commands(stream: UnixStream, event_bus: EventBusHandle) {
+        let mut reader = BufReader::new(stream);
+        while let Ok(frame) = daemon_socket::read_frame(&mut reader) {
+            if frame.event_type == EventType::ControlCommand {
+                event_bus.publish(EventType::ControlCommand, frame.data);
+            } else {
+                warn!("daemon client sent '{}', ignoring it", frame.event_type);
+            }
+        }
+    }
+
+    fn serve(self: &Arc<Self>) -> Result<(), Box<dyn std::error::Error>> {
+        info!(
+            "serving daemon clients on {:?}",
+            daemon_socket::socket_path()?
+        );
+
+        let (tx, rx) = mpsc::channel();
+        for event_type in daemon_socket::FORWARDED_EVENTS {
+            self.forward_event(event_type, tx.clone());
+        }
+
+        {
+            let server = self.clone();
+            thread::spawn(move || {
+                for frame in rx {
+                    server.broadcast(frame);
+                }
+            });
+        }
+
+        for stream in self.listener.incoming() {
+            match stream {
+                Ok(stream) => self.welcome(stream),
+                Err(err) => warn!("failed to accept daemon client: {err}"),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Runnable for DaemonServer {
+    fn run(self: Arc<Self>) -> JoinHandle<()> {
+        thread::spawn(move || {
+            info!("starting DaemonServer thread");
+            if let Err(err) = self.serve() {
+                error!("DaemonServer failed: {err}");
+            }
+            info!("DaemonServer thread is stopping");
+        })
+    }
+}
@@ -1,39 +1,54 @@
 use std::{
-    sync::Arc,
+    mem,
+    sync::{Arc, Mutex},
     thread::{self, JoinHandle},
     time::Duration,
 };
 
 use bincode::config;
-use dbus::{arg::RefArg, blocking::Connection, message::MatchRule, Message};
+use dbus::{
+    arg::RefArg, blocking::Connection, channel::MatchingReceiver, message::MatchRule, Message,
+};
 use log::{debug, error, info, warn};
 
 use crate::{
     event_bus::{EventBusHandle, EventType},
-    interfaces::dbus_client::DBusClient,
+    interfaces::{bus, dbus_client::DBusClient},
     models::{
-        args::Args, mpris_identity::MprisIdentity, mpris_metadata::MprisMetadata,
-        mpris_playback::MprisPlayback, mpris_rate::MprisRate, mpris_seeked::MprisSeeked,
+        args::Args, bus_status::BusStatus, mpris_identity::MprisIdentity,
+        mpris_metadata::MprisMetadata, mpris_playback::MprisPlayback, mpris_rate::MprisRate,
+        mpris_seeked::MprisSeeked, playback_state::PlaybackState,
     },
 };
 
-use super::runnable::Runnable;
+use super::{mpris_proxy, runnable::Runnable};
 
 pub struct DBusMonitor {
     args: Arc<Args>,
     event_bus: EventBusHandle,
     dbus_client: Arc<DBusClient>,
+    /// The bus status we last told everyone about
+    announced: Mutex<Option<BusStatus>>,
+    /// Whether `[mpris_proxy]` is enabled, so its signals must be told apart from the players'
+    watch_proxy: bool,
+    /// Unique name of whoever serves the MPRIS proxy right now
+    proxy_owner: Arc<Mutex<Option<String>>>,
 }
 
-// TODO: we also need to discover players when we run the program initially
-// who should handle that? the monitor, or a new service?
-
 impl DBusMonitor {
-    pub fn new(args: Arc<Args>, event_bus: EventBusHandle, dbus_client: Arc<DBusClient>) -> Self {
+    pub fn new(
+        args: Arc<Args>,
+        event_bus: EventBusHandle,
+        dbus_client: Arc<DBusClient>,
+        watch_proxy: bool,
+    ) -> Self {
         Self {
             args,
             event_bus,
             dbus_client,
+            announced: Mutex::new(None),
+            watch_proxy,
+            proxy_owner: Arc::new(Mutex::new(None)),
         }
     }
 
@@ -80,10 +95,7 @@ impl DBusMonitor {
         };
 
         match dbus_client.query_mediaplayer_identity(&sender) {
-            Ok(identity) => args
-                .whitelist
-                .iter()
-                .any(|w| identity.to_lowercase().contains(&w.to_lowercase())),
+            Ok(identity) => args.is_whitelisted(&identity),
             Err(err) => {
                 error!("failed to query media player identity, handling it anyway: {err}");
                 true
@@ -91,12 +103,81 @@ impl DBusMonitor {
         }
     }
 
+    /// The proxy mirrors the active player, so treating it as a player would make it follow itself
+    fn is_mpris_proxy(proxy_owner: &Mutex<Option<String>>, msg: &Message) -> bool {
+        let Some(sender) = msg.sender() else {
+            return false;
+        };
+
+        proxy_owner
+            .lock()
+            .is_ok_and(|owner| owner.as_deref() == Some(&*sender))
+    }
+
+    /// Follows the MPRIS names as they change hands. A player that quits is announced as stopped,
+    /// as not every player says so before it goes, and `proxy_owner` is kept up to date so telling
+    /// the proxy's signals apart doesn't take a round trip to the bus
+    fn follow_name_owners(&self, conn: &Connection) -> Result<(), dbus::Error> {
+        conn.add_match_no_cb(
+            "type='signal',sender='org.freedesktop.DBus',interface='org.freedesktop.DBus',member='NameOwnerChanged',arg0namespace='org.mpris.MediaPlayer2'",
+        )?;
+
+        let proxy_owner = self.watch_proxy.then(|| self.proxy_owner.clone());
+        let event_bus = self.event_bus.clone();
+        let rule = MatchRule::new_signal("org.freedesktop.DBus", "NameOwnerChanged")
+            .with_sender("org.freedesktop.DBus");
+        conn.start_receive(
+            rule,
+            Box::new(move |msg: Message, _: &Connection| {
+                // the name, its old owner and its new owner, which is empty once it's released
+                let (Some(name), Some(old_owner), Some(new_owner)) =
+                    msg.get3::<String, String, String>()
+                else {
+                    return true;
+                };
+
+                if name == mpris_proxy::BUS_NAME {
+                    if let Some(Ok(mut owner)) = proxy_owner.as_ref().map(|o| o.lock()) {
+                        *owner = Some(new_owner).filter(|o| !o.is_empty());
+                    }
+                } else if new_owner.is_empty() && !old_owner.is_empty() {
+                    debug!("'{name}' ({old_owner}) is gone");
+                    let stopped =
+                        MprisPlayback::new_with_playing(old_owner, Some(PlaybackState::Stopped));
+                    match bincode::encode_to_vec(stopped, config::standard()) {
+                        Ok(encoded) => event_bus.publish(EventType::PlaybackChanged, encoded),
+                        Err(err) => error!("failed to encode MPRIS data: {err}"),
+                    }
+                }
+                true
+            }),
+        );
+
+        if self.watch_proxy {
+            // whoever claimed the name before we started listening
+            let owner = self
+                .dbus_client
+                .query_name_owner(mpris_proxy::BUS_NAME)
+                .ok();
+            if let Ok(mut proxy_owner) = self.proxy_owner.lock() {
+                *proxy_owner = owner;
+            }
+        }
+        Ok(())
+    }
+
     fn handle_on_match(
         args: Arc<Args>,
         dbus_client: Arc<DBusClient>,
+        proxy_owner: &Mutex<Option<String>>,
         msg: &Message,
         event_bus: EventBusHandle,
     ) -> bool {
+        if DBusMonitor::is_mpris_proxy(proxy_owner, msg) {
+            debug!("ignoring our own MPRIS proxy");
+            return true;
+        }
+
         if !DBusMonitor::should_handle_sender(args, dbus_client, msg) {
             debug!("ignoring sender, not in whitelist");
             return true;
@@ -149,9 +230,29 @@ impl DBusMonitor {
         true
     }
 
+    /// Tells everyone whether the players can be reached, so PlayerManager can look for players
+    /// once we're connected and the bar can say when we're not
+    fn publish_bus_status(&self, status: BusStatus) {
+        // every failed attempt to reconnect would announce the same thing again
+        let Ok(mut announced) = self.announced.lock() else {
+            return;
+        };
+        if announced.as_ref().map(mem::discriminant) == Some(mem::discriminant(&status)) {
+            return;
+        }
+        *announced = Some(status.clone());
+
+        match bincode::encode_to_vec(&status, config::standard()) {
+            Ok(encoded) => self
+                .event_bus
+                .publish_retained(EventType::BusStatusChanged, encoded),
+            Err(err) => error!("failed to encode bus status: {err}"),
+        }
+    }
+
     // TODO: some of this should be handled by DBusClient
     pub fn begin_monitoring(&self) -> Result<(), Box<dyn std::error::Error>> {
-        let conn = Connection::new_session()?;
+        let conn = self.dbus_client.bus().connect()?;
 
         let rules: Vec<MatchRule> = vec![
             MatchRule::new()
@@ -170,10 +271,12 @@ impl DBusMonitor {
             let event_bus = self.event_bus.clone();
             let dbus_client = self.dbus_client.clone();
             let args = self.args.clone();
+            let proxy_owner = self.proxy_owner.clone();
             match conn.add_match(rule, move |_: (), _, msg| {
                 DBusMonitor::handle_on_match(
                     args.clone(),
                     dbus_client.clone(),
+                    &proxy_owner,
                     msg,
                     event_bus.clone(),
                 )
@@ -186,14 +289,15 @@ impl DBusMonitor {
             };
         }
 
+        // the client's connection may be from before the bus went away
+        self.dbus_client.reconnect();
+        self.follow_name_owners(&conn)?;
+        info!("monitoring MPRIS players on {}", self.dbus_client.bus());
+        self.publish_bus_status(BusStatus::Connected);
+
         loop {
-            match conn.process(Duration::from_millis(1000)) {
-                Ok(res) => (),
-                Err(err) => warn!("failed to process DBus connection: {err}"),
-            }
+            conn.process(Duration::from_millis(1000))?;
         }
-
-        Ok(())
     }
 }
 
@@ -201,7 +305,13 @@ impl Runnable for DBusMonitor {
     fn run(self: Arc<Self>) -> JoinHandle<()> {
         thread::spawn(move || {
             info!("starting DBusMonitor thread");
-            let result = self.begin_monitoring();
+            bus::supervise("DBusMonitor", || {
+                let result = self.begin_monitoring();
+                if let Err(err) = &result {
+                    self.publish_bus_status(BusStatus::Disconnected(err.to_string()));
+                }
+                result
+            });
             info!("DBusMonitor thread is stopping");
         })
     }
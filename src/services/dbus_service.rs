@@ -0,0 +1,324 @@
+use std::{
+    error::Error,
+    sync::Arc,
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use bincode::config;
+use dbus::{
+    arg::{PropMap, RefArg, Variant},
+    blocking::{
+        stdintf::org_freedesktop_dbus::{PropertiesPropertiesChanged, RequestNameReply},
+        Connection,
+    },
+    channel::Sender,
+    message::{MessageType, SignalArgs},
+};
+use dbus_crossroads::{Crossroads, IfaceBuilder};
+use log::{debug, error, info, warn};
+
+use crate::{
+    event_bus::{EventBusHandle, EventType},
+    interfaces::{
+        bus::{self, Bus},
+        control_socket,
+    },
+    models::{
+        bus_status::BusStatus, control_command::ControlCommand, playback_state::PlaybackState,
+        player_state::PlayerState,
+    },
+};
+
+use super::runnable::Runnable;
+
+pub const BUS_NAME: &str = "io.github.waybar_module_music";
+pub const OBJECT_PATH: &str = "/io/github/waybar_module_music";
+pub const INTERFACE: &str = "io.github.waybar_module_music";
+
+/// Serves the displayed player's state and controls on the session bus,
+/// for scripts and widgets that want to follow the bar
+pub struct DBusService {
+    event_bus: EventBusHandle,
+    bus: Bus,
+}
+
+/// The displayed player, as exposed by our properties
+#[derive(Default)]
+struct Properties {
+    state: Option<PlayerState>,
+}
+
+impl Properties {
+    fn map<T: Default>(&self, f: impl Fn(&PlayerState) -> T) -> T {
+        self.state.as_ref().map(f).unwrap_or_default()
+    }
+
+    /// Nothing but the status, once nothing is playing or the players can't be reached,
+    /// so scripts don't keep reading the last track
+    fn stopped() -> PlayerState {
+        PlayerState::new(
+            String::new(),
+            String::new(),
+            String::new(),
+            String::new(),
+            String::new(),
+            Some(PlaybackState::Stopped),
+            0,
+            0,
+        )
+    }
+
+    fn status(state: &PlayerState) -> String {
+        state
+            .playing
+            .clone()
+            .unwrap_or(PlaybackState::Stopped)
+            .to_string()
+    }
+}
+
+impl DBusService {
+    pub fn new(event_bus: EventBusHandle, bus: Bus) -> Self {
+        Self { event_bus, bus }
+    }
+
+    fn register_interface(&self, cr: &mut Crossroads) -> dbus_crossroads::IfaceToken<Properties> {
+        let event_bus = self.event_bus.clone();
+
+        cr.register(INTERFACE, move |b: &mut IfaceBuilder<Properties>| {
+            // changes are announced by hand when a new state comes in, see `announce_changes`
+            b.property("PlayerName")
+                .get(|_, props| Ok(props.map(|s| s.player_name.clone())));
+            b.property("PlayerId")
+                .get(|_, props| Ok(props.map(|s| s.player_id.clone())));
+            b.property("PlaybackStatus").get(|_, props| {
+                Ok(props
+                    .state
+                    .as_ref()
+                    .map(Properties::status)
+                    .unwrap_or_else(|| PlaybackState::Stopped.to_string()))
+            });
+            b.property("Artist")
+                .get(|_, props| Ok(props.map(|s| s.artist.clone())));
+            b.property("Title")
+                .get(|_, props| Ok(props.map(|s| s.title.clone())));
+            b.property("Album")
+                .get(|_, props| Ok(props.map(|s| s.album.clone())));
+            // microseconds, like MPRIS
+            b.property("Length")
+                .get(|_, props| Ok(props.map(|s| s.length as i64)));
+            b.property("Position")
+                .emits_changed_false()
+                .get(|_, props| Ok(props.map(|s| s.position as i64)));
+            b.property("ArtUrl")
+                .get(|_, props| Ok(props.map(|s| s.art_url.clone())));
+            b.property("Url")
+                .get(|_, props| Ok(props.map(|s| s.url.clone())));
+            b.property("Pinned")
+                .get(|_, props| Ok(props.map(|s| s.pinned)));
+
+            for (method, command) in [
+                ("PlayPause", ControlCommand::PlayPause),
+                ("Play", ControlCommand::Play),
+                ("Pause", ControlCommand::Pause),
+                ("Next", ControlCommand::Next),
+                ("Previous", ControlCommand::Previous),
+                ("Pin", ControlCommand::Pin),
+                ("Unpin", ControlCommand::Unpin),
+                ("NextPlayer", ControlCommand::NextPlayer),
+                ("PreviousPlayer", ControlCommand::PreviousPlayer),
+            ] {
+                let event_bus = event_bus.clone();
+                b.method(method, (), (), move |_, _, ()| {
+                    info!("received D-Bus command '{command}'");
+                    // to every instance, like the command line does
+                    match control_socket::send_command(&command) {
+                        Ok(reached) if reached > 0 => return Ok(()),
+                        Ok(_) => warn!("no control sockets found, handling '{command}' ourselves"),
+                        Err(err) => warn!("failed to send '{command}' to every instance: {err}"),
+                    }
+                    match bincode::encode_to_vec(&command, config::standard()) {
+                        Ok(encoded) => event_bus.publish(EventType::ControlCommand, encoded),
+                        Err(err) => error!("failed to encode control command: {err}"),
+                    }
+                    Ok(())
+                });
+            }
+        })
+    }
+
+    /// Properties that differ between the two states. Position is left out,
+    /// as it changes constantly and MPRIS doesn't announce it either
+    fn changed_properties(previous: Option<&PlayerState>, current: &PlayerState) -> PropMap {
+        fn insert_if_changed<T: RefArg + PartialEq + 'static>(
+            changed: &mut PropMap,
+            name: &str,
+            previous: Option<T>,
+            current: T,
+        ) {
+            if previous.as_ref() != Some(&current) {
+                changed.insert(name.to_string(), Variant(Box::new(current)));
+            }
+        }
+
+        let mut changed = PropMap::new();
+        let prev = |f: fn(&PlayerState) -> String| previous.map(f);
+        insert_if_changed(
+            &mut changed,
+            "PlayerName",
+            prev(|s| s.player_name.clone()),
+            current.player_name.clone(),
+        );
+        insert_if_changed(
+            &mut changed,
+            "PlayerId",
+            prev(|s| s.player_id.clone()),
+            current.player_id.clone(),
+        );
+        insert_if_changed(
+            &mut changed,
+            "PlaybackStatus",
+            prev(Properties::status),
+            Properties::status(current),
+        );
+        insert_if_changed(
+            &mut changed,
+            "Artist",
+            prev(|s| s.artist.clone()),
+            current.artist.clone(),
+        );
+        insert_if_changed(
+            &mut changed,
+            "Title",
+            prev(|s| s.title.clone()),
+            current.title.clone(),
+        );
+        insert_if_changed(
+            &mut changed,
+            "Album",
+            prev(|s| s.album.clone()),
+            current.album.clone(),
+        );
+        insert_if_changed(
+            &mut changed,
+            "Length",
+            previous.map(|s| s.length as i64),
+            current.length as i64,
+        );
+        insert_if_changed(
+            &mut changed,
+            "ArtUrl",
+            prev(|s| s.art_url.clone()),
+            current.art_url.clone(),
+        );
+        insert_if_changed(
+            &mut changed,
+            "Url",
+            prev(|s| s.url.clone()),
+            current.url.clone(),
+        );
+        insert_if_changed(
+            &mut changed,
+            "Pinned",
+            previous.map(|s| s.pinned),
+            current.pinned,
+        );
+        changed
+    }
+
+    fn announce_changes(conn: &Connection, changed: PropMap) {
+        if changed.is_empty() {
+            return;
+        }
+
+        let signal = PropertiesPropertiesChanged {
+            interface_name: INTERFACE.to_string(),
+            changed_properties: changed,
+            invalidated_properties: vec![],
+        };
+        if conn
+            .send(signal.to_emit_message(&OBJECT_PATH.into()))
+            .is_err()
+        {
+            warn!("failed to send PropertiesChanged signal");
+        }
+    }
+
+    fn serve(&self) -> Result<(), Box<dyn Error>> {
+        let conn = self.bus.connect()?;
+        // another instance already serving is fine, the first one wins
+        if conn.request_name(BUS_NAME, false, false, true)? != RequestNameReply::PrimaryOwner {
+            info!("'{BUS_NAME}' is taken by another instance, not serving it");
+            return Ok(());
+        }
+        let Some(rx) = self.event_bus.subscribe(EventType::PlayerStateChanged) else {
+            return Err("failed to subscribe to PlayerStateChanged listener".into());
+        };
+        let Some(bus_rx) = self.event_bus.subscribe(EventType::BusStatusChanged) else {
+            return Err("failed to subscribe to BusStatusChanged listener".into());
+        };
+        info!("serving on {} as '{BUS_NAME}'", self.bus);
+
+        let mut cr = Crossroads::new();
+        let iface = self.register_interface(&mut cr);
+        cr.insert(OBJECT_PATH, &[iface], Properties::default());
+
+        loop {
+            // handle method calls as they come in, and check for new states in between
+            if conn
+                .channel()
+                .read_write(Some(Duration::from_millis(100)))
+                .is_err()
+            {
+                return Err("lost the D-Bus connection".into());
+            }
+            while let Some(msg) = conn.channel().pop_message() {
+                if msg.msg_type() == MessageType::MethodCall {
+                    let _ = cr.handle_message(msg, &conn);
+                }
+            }
+
+            let mut states = vec![];
+            for encoded in rx.try_iter() {
+                match bincode::decode_from_slice::<PlayerState, _>(&encoded[..], config::standard())
+                {
+                    Ok((state, _)) if state.is_stopped() => states.push(Properties::stopped()),
+                    Ok((state, _)) => states.push(state),
+                    Err(err) => warn!("failed to decode message in DBusService: {err}"),
+                }
+            }
+
+            // nothing more comes from PlayerManager once the bus is gone
+            for encoded in bus_rx.try_iter() {
+                match bincode::decode_from_slice::<BusStatus, _>(&encoded[..], config::standard()) {
+                    Ok((BusStatus::Disconnected(_), _)) => states.push(Properties::stopped()),
+                    Ok((BusStatus::Connected, _)) => (),
+                    Err(err) => warn!("failed to decode message in DBusService: {err}"),
+                }
+            }
+            for state in states {
+                let Some(props) = cr.data_mut::<Properties>(&OBJECT_PATH.into()) else {
+                    return Err("our object path disappeared".into());
+                };
+                let changed = DBusService::changed_properties(props.state.as_ref(), &state);
+                props.state = Some(state);
+
+                if !changed.is_empty() {
+                    debug!("announcing {} changed properties", changed.len());
+                }
+                DBusService::announce_changes(&conn, changed);
+            }
+        }
+    }
+}
+
+impl Runnable for DBusService {
+    fn run(self: Arc<Self>) -> JoinHandle<()> {
+        thread::spawn(move || {
+            info!("starting DBusService thread");
+            bus::supervise("DBusService", || self.serve());
+            info!("DBusService thread is stopping");
+        })
+    }
+}
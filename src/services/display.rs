@@ -5,7 +5,25 @@ use crate::{
     effects::{ellipsis::Ellipsis, marquee::Marquee, text_effect::TextEffect},
     event_bus::{EventBusHandle, EventType},
     models::{
-        args::Args, config::Config, playback_state::PlaybackState, player_state::PlayerState,
+        args::{Args, OutputMode},
+        bus_status::BusStatus,
+        cached_art::CachedArt,
+        config::Config,
+        listening_stats::ListeningStats,
+        now_playing::NowPlaying,
+        playback_state::PlaybackState,
+        player_state::PlayerState,
+        track_lyrics::TrackLyrics,
+    },
+    outputs::{
+        i3bar::I3bar,
+        json_stream::JsonStream,
+        output::{Output, Update},
+        plain::Plain,
+        polybar::Polybar,
+        preview::Preview,
+        waybar::Waybar,
+        yambar::Yambar,
     },
     utils::time,
 };
@@ -14,67 +32,176 @@ use super::runnable::Runnable;
 use std::{
     collections::HashMap,
     sync::{
-        mpsc::{self, Receiver, Sender},
+        mpsc::{self, Receiver, RecvTimeoutError, Sender},
         Arc,
     },
     thread::{self},
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 #[derive(Debug)]
 enum DisplayMessages {
     PlayerStateChanged(PlayerState),
+    PlayerListChanged(Vec<PlayerState>),
+    ListeningStatsChanged(ListeningStats),
+    ArtCached(CachedArt),
+    LyricsLoaded(TrackLyrics),
+    BusStatusChanged(BusStatus),
     AnimationDue,
 }
 
+/// Every placeholder `--format` and `--player-format` may use, like "%artist%"
+pub const PLACEHOLDERS: [&str; 15] = [
+    "icon",
+    "title",
+    "artist",
+    "album",
+    "player",
+    "player-icon",
+    "length",
+    "position",
+    "index",
+    "player-count",
+    "today-listened",
+    "today-tracks",
+    "lyric",
+    "art-path",
+    "art-color",
+];
+
+/// Text effects for every placeholder of a single player
+type Fields = HashMap<&'static str, TextEffect>;
+
+/// Placeholder data published by other services, rather than coming from the players themselves
+#[derive(Default)]
+struct Extras {
+    stats: ListeningStats,
+    /// Local copies of album art and their colors, keyed by the players' art URL
+    art: HashMap<String, CachedArt>,
+    /// The lyrics of each player's current track, keyed by player ID
+    lyrics: HashMap<String, TrackLyrics>,
+    /// When the players' positions were last updated, to follow along with lyrics in between updates
+    players_updated_at: Option<Instant>,
+    /// Why we can't reach the players, while we can't
+    bus_error: Option<String>,
+}
+
+impl Extras {
+    fn lyrics_for(&self, state: &PlayerState) -> Option<&TrackLyrics> {
+        self.lyrics
+            .get(&state.player_id)
+            .filter(|lyrics| lyrics.is_for(state))
+    }
+
+    /// Where the player should be by now, in milliseconds
+    fn position_ms(&self, state: &PlayerState) -> u64 {
+        let position = (state.position / 1000) as u64;
+        match self.players_updated_at {
+            Some(updated_at) if state.playing == Some(PlaybackState::Playing) => {
+                position + updated_at.elapsed().as_millis() as u64
+            }
+            _ => position,
+        }
+    }
+
+    /// How long until the lyric line changes for any of the given players
+    fn next_lyric_change(&self, players: &[PlayerState]) -> Option<Duration> {
+        players
+            .iter()
+            .filter(|state| state.playing == Some(PlaybackState::Playing))
+            .filter_map(|state| {
+                let position = self.position_ms(state);
+                self.lyrics_for(state)?
+                    .lyrics
+                    .as_ref()?
+                    .next_line_ms(position)
+                    .map(|next| Duration::from_millis(next - position))
+            })
+            .min()
+    }
+}
+
 pub struct Display {
     args: Arc<Args>,
     config: Arc<Config>,
     event_bus: EventBusHandle,
+    output: Box<dyn Output>,
 }
 
 impl Display {
     pub fn new(args: Arc<Args>, config: Arc<Config>, event_bus: EventBusHandle) -> Self {
+        let output: Box<dyn Output> = match args.output {
+            _ if args.is_preview() => Box::new(Preview),
+            OutputMode::Waybar => Box::new(Waybar),
+            OutputMode::I3bar => Box::new(I3bar),
+            OutputMode::Polybar => Box::new(Polybar),
+            OutputMode::Yambar => Box::new(Yambar),
+            OutputMode::Plain => Box::new(Plain),
+            OutputMode::JsonStream => Box::new(JsonStream),
+        };
+
         Self {
             args,
             config,
             event_bus,
+            output,
         }
     }
 
-    fn escape_pango(&self, text_to_escape: &str) -> String {
-        text_to_escape
-            .chars()
-            .map(|x| match x {
-                // find and replace all special characters with escaped pango sequences
-                '&' => "&amp;".to_string(),
-                '<' => "&lt;".to_string(),
-                '>' => "&gt;".to_string(),
-                '\'' => "&#39;".to_string(),
-                '"' => "&quot;".to_string(),
-                _ => x.to_string(),
-            })
-            .collect()
+    /// Print a single update for the given players and leave it at that, for `--once`
+    pub fn print_once(&self, players: &[PlayerState]) {
+        let mut fields = HashMap::new();
+        self.update_fields(players, &mut fields);
+        self.draw(players, &mut fields, &Extras::default());
     }
 
     fn init_worker(self: Arc<Self>) {
-        println!(
-            "{}",
-            self.format_json_output(&self.args.stopped_label, "stopped")
+        if let Some(header) = self.output.header() {
+            println!("{header}");
+        }
+        self.print(
+            &self.output.escape(&self.args.stopped_label),
+            &[String::from("stopped")],
+            &[],
         );
 
         let (tx, rx) = mpsc::channel();
         let (effect_tx, effect_rx) = mpsc::channel();
 
-        if let Some(rx) = self.event_bus.subscribe(EventType::PlayerStateChanged) {
-            let tx = tx.clone();
-            thread::spawn(move || {
-                Display::listen_player_state(rx, tx);
-            });
+        if self.args.multi_player {
+            self.subscribe_to_event(
+                EventType::PlayerListChanged,
+                tx.clone(),
+                DisplayMessages::PlayerListChanged,
+            );
         } else {
-            error!("failed to subscribe to PlayerStateChanged listener");
+            self.subscribe_to_event(
+                EventType::PlayerStateChanged,
+                tx.clone(),
+                DisplayMessages::PlayerStateChanged,
+            );
         }
 
+        self.subscribe_to_event(
+            EventType::ListeningStatsChanged,
+            tx.clone(),
+            DisplayMessages::ListeningStatsChanged,
+        );
+
+        self.subscribe_to_event(EventType::ArtCached, tx.clone(), DisplayMessages::ArtCached);
+
+        self.subscribe_to_event(
+            EventType::LyricsLoaded,
+            tx.clone(),
+            DisplayMessages::LyricsLoaded,
+        );
+
+        self.subscribe_to_event(
+            EventType::BusStatusChanged,
+            tx.clone(),
+            DisplayMessages::BusStatusChanged,
+        );
+
         {
             let tx = tx.clone();
             let effect_speed = self.args.effect_speed as u64;
@@ -83,10 +210,10 @@ impl Display {
             });
         }
 
-        self.listen_for_updates(rx, effect_tx, self.init_fields());
+        self.listen_for_updates(rx, effect_tx);
     }
 
-    fn init_fields(&self) -> HashMap<&str, TextEffect> {
+    fn init_fields(&self) -> Fields {
         let mut fields = HashMap::new();
 
         // FIXME: I'm sure this could be done better
@@ -156,26 +283,39 @@ impl Display {
         }
     }
 
-    fn listen_player_state(rx: Receiver<Vec<u8>>, tx: Sender<DisplayMessages>) {
-        loop {
-            let msg = rx.recv();
-            let (state, _): (PlayerState, usize) = match msg {
-                Ok(encoded) => {
-                    bincode::decode_from_slice(&encoded[..], config::standard()).unwrap()
-                }
-                Err(err) => {
-                    warn!("failed to decode message in Display: {err}");
-                    continue;
-                }
-            };
-
-            if let Err(err) = tx.send(DisplayMessages::PlayerStateChanged(state)) {
-                warn!("failed to send DisplayMessages: {err}");
+    fn subscribe_to_event<T, F>(
+        &self,
+        event_type: EventType,
+        tx: Sender<DisplayMessages>,
+        message_constructor: F,
+    ) where
+        T: bincode::Decode<()>,
+        F: Fn(T) -> DisplayMessages + Send + 'static,
+    {
+        match self.event_bus.subscribe(event_type.clone()) {
+            Some(rx) => {
+                thread::spawn(move || loop {
+                    let msg = rx.recv();
+                    let (state, _): (T, usize) = match msg {
+                        Ok(encoded) => {
+                            bincode::decode_from_slice(&encoded[..], config::standard()).unwrap()
+                        }
+                        Err(err) => {
+                            warn!("failed to decode message in Display: {err}");
+                            continue;
+                        }
+                    };
+
+                    if let Err(err) = tx.send(message_constructor(state)) {
+                        warn!("failed to send DisplayMessages: {err}");
+                    }
+                });
             }
+            None => error!("failed to subscribe to {event_type} listener"),
         }
     }
 
-    fn set_text_effect_field(fields: &mut HashMap<&str, TextEffect>, value: &str, field: &str) {
+    fn set_text_effect_field(fields: &mut Fields, value: &str, field: &str) {
         match fields.get_mut(field) {
             Some(field) => {
                 if field.current_text() != value {
@@ -187,91 +327,163 @@ impl Display {
         }
     }
 
-    fn should_effects_be_redrawn(&self, fields: &HashMap<&str, TextEffect>) -> bool {
-        fields.iter().any(|(_, v)| v.has_active_effects())
+    fn should_effects_be_redrawn(&self, fields: &HashMap<String, Fields>) -> bool {
+        fields
+            .values()
+            .flat_map(|f| f.values())
+            .any(|v| v.has_active_effects())
     }
 
-    fn listen_for_updates(
-        &self,
-        rx: Receiver<DisplayMessages>,
-        effect_tx: Sender<bool>,
-        mut fields: HashMap<&str, TextEffect>,
-    ) {
-        let mut player_state: Option<PlayerState> = None;
+    /// Feed the latest player data into each player's text effects,
+    /// dropping the effects of players that are gone
+    fn update_fields(&self, players: &[PlayerState], fields: &mut HashMap<String, Fields>) {
+        fields.retain(|id, _| players.iter().any(|p| &p.player_id == id));
+
+        for state in players {
+            let fields = fields
+                .entry(state.player_id.clone())
+                .or_insert_with(|| self.init_fields());
+
+            Display::set_text_effect_field(fields, &state.title, "title");
+            Display::set_text_effect_field(fields, &state.artist, "artist");
+            Display::set_text_effect_field(fields, &state.album, "album");
+            Display::set_text_effect_field(fields, &state.player_name, "player");
+            Display::set_text_effect_field(
+                fields,
+                &time::microseconds_to_formatted_time(state.length as u128),
+                "length",
+            );
+            Display::set_text_effect_field(
+                fields,
+                &time::microseconds_to_formatted_time(state.position),
+                "position",
+            );
+            Display::set_text_effect_field(
+                fields,
+                self.config
+                    .get_player_icon_by_partial_match(&state.player_name),
+                "player-icon",
+            );
+        }
+    }
+
+    fn listen_for_updates(&self, rx: Receiver<DisplayMessages>, effect_tx: Sender<bool>) {
+        let mut players: Vec<PlayerState> = vec![];
+        let mut fields: HashMap<String, Fields> = HashMap::new();
+        let mut extras = Extras::default();
 
         loop {
-            let msg = match rx.recv() {
-                Ok(msg) => msg,
-                Err(err) => {
-                    warn!("failed to recieve message: {err}");
-                    continue;
-                }
+            // wake up right as the next lyric line starts, rather than waiting for the next update
+            let msg = match extras.next_lyric_change(&players) {
+                Some(wait) => match rx.recv_timeout(wait) {
+                    Ok(msg) => msg,
+                    Err(RecvTimeoutError::Timeout) => {
+                        self.draw(&players, &mut fields, &extras);
+                        continue;
+                    }
+                    Err(err) => {
+                        warn!("failed to recieve message: {err}");
+                        continue;
+                    }
+                },
+                None => match rx.recv() {
+                    Ok(msg) => msg,
+                    Err(err) => {
+                        warn!("failed to recieve message: {err}");
+                        continue;
+                    }
+                },
             };
 
             debug!("msg receieved: {:?}", msg);
 
-            match msg {
-                DisplayMessages::PlayerStateChanged(state) => {
-                    Display::set_text_effect_field(&mut fields, &state.title, "title");
-                    Display::set_text_effect_field(&mut fields, &state.artist, "artist");
-                    Display::set_text_effect_field(&mut fields, &state.album, "album");
-                    Display::set_text_effect_field(&mut fields, &state.player_name, "player");
-                    Display::set_text_effect_field(
-                        &mut fields,
-                        &time::microseconds_to_formatted_time(state.length as u128),
-                        "length",
-                    );
-                    Display::set_text_effect_field(
-                        &mut fields,
-                        &time::microseconds_to_formatted_time(state.position),
-                        "position",
-                    );
-                    Display::set_text_effect_field(
-                        &mut fields,
-                        self.config
-                            .get_player_icon_by_partial_match(&state.player_name),
-                        "player-icon",
-                    );
-                    player_state = Some(state);
-                    self.draw(&player_state, &mut fields);
-                    if let Err(err) = effect_tx.send(self.should_effects_be_redrawn(&fields)) {
-                        error!("failed to notify effects thread: {err}");
-                    }
+            players = match msg {
+                DisplayMessages::PlayerStateChanged(state) => vec![state],
+                DisplayMessages::PlayerListChanged(states) => states,
+                DisplayMessages::ListeningStatsChanged(stats) => {
+                    extras.stats = stats;
+                    self.draw(&players, &mut fields, &extras);
+                    continue;
+                }
+                DisplayMessages::ArtCached(art) => {
+                    extras.art.insert(art.art_url.clone(), art);
+                    self.draw(&players, &mut fields, &extras);
+                    continue;
+                }
+                DisplayMessages::LyricsLoaded(lyrics) => {
+                    extras.lyrics.insert(lyrics.player_id.clone(), lyrics);
+                    self.draw(&players, &mut fields, &extras);
+                    continue;
+                }
+                DisplayMessages::BusStatusChanged(BusStatus::Connected) => {
+                    // the players come back on their own, once PlayerManager finds them
+                    extras.bus_error = None;
+                    self.draw(&players, &mut fields, &extras);
+                    continue;
+                }
+                DisplayMessages::BusStatusChanged(BusStatus::Disconnected(reason)) => {
+                    extras.bus_error = Some(reason);
+                    vec![]
                 }
                 DisplayMessages::AnimationDue => {
                     if self.should_effects_be_redrawn(&fields) {
-                        fields.iter_mut().for_each(|(_, v)| {
-                            v.should_redraw();
-                        });
-                        self.draw(&player_state, &mut fields)
+                        fields
+                            .values_mut()
+                            .flat_map(|f| f.values_mut())
+                            .for_each(|v| {
+                                v.should_redraw();
+                            });
+                        self.draw(&players, &mut fields, &extras)
                     }
+                    continue;
                 }
+            };
+
+            extras.players_updated_at = Some(Instant::now());
+            extras
+                .art
+                .retain(|art_url, _| players.iter().any(|p| &p.art_url == art_url));
+            self.update_fields(&players, &mut fields);
+            self.draw(&players, &mut fields, &extras);
+            if let Err(err) = effect_tx.send(self.should_effects_be_redrawn(&fields)) {
+                error!("failed to notify effects thread: {err}");
             }
         }
     }
 
-    fn get_class(&self, state: &PlayerState) -> String {
-        if let Some(playing) = state.playing.clone() {
+    fn get_classes(&self, state: &PlayerState) -> Vec<String> {
+        let mut classes = vec![if let Some(playing) = state.playing.clone() {
             playing.to_string()
         } else {
             String::from("stopped")
+        }];
+
+        if state.pinned {
+            classes.push(String::from("pinned"));
         }
+
+        classes
     }
 
-    /// Create the final output JSON, in the format that Waybar expects
-    fn format_json_output(&self, text: &str, class: &str) -> String {
-        let text = self.escape_pango(text);
-        let class = self.escape_pango(class);
-        format!(
-            "{{\"text\": \"{}\", \"tooltip\": \"{}\", \"class\": \"{}\", \"alt\": \"{}\"}}",
-            text, "", class, ""
-        )
+    /// Write an update in the format the bar expects.
+    /// The text may contain the output's markup, so anything that should show up literally must already be escaped
+    fn print(&self, text: &str, classes: &[String], players: &[NowPlaying]) {
+        let update = Update {
+            text,
+            classes,
+            players,
+        };
+        println!("{}", self.output.format(&update));
     }
 
     fn populate_using_placeholders(
         &self,
+        format: &str,
         player_state: &PlayerState,
-        fields: &mut HashMap<&str, TextEffect>,
+        fields: &mut Fields,
+        index: usize,
+        player_count: usize,
+        extras: &Extras,
     ) -> String {
         let replacements: HashMap<&str, String> = [
             (
@@ -334,50 +546,175 @@ impl Display {
                     .unwrap()
                     .draw(&time::microseconds_to_formatted_time(player_state.position)),
             ),
+            ("index", index.to_string()),
+            ("player-count", player_count.to_string()),
+            (
+                "today-listened",
+                time::seconds_to_formatted_duration(extras.stats.listened),
+            ),
+            ("today-tracks", extras.stats.tracks.to_string()),
+            (
+                "lyric",
+                extras
+                    .lyrics_for(player_state)
+                    .and_then(|lyrics| lyrics.lyrics.as_ref())
+                    .and_then(|lyrics| lyrics.line_at(extras.position_ms(player_state)))
+                    .unwrap_or_default()
+                    .to_string(),
+            ),
+            (
+                "art-path",
+                extras
+                    .art
+                    .get(&player_state.art_url)
+                    .map(|art| art.path.clone())
+                    .unwrap_or_default(),
+            ),
+            (
+                "art-color",
+                extras
+                    .art
+                    .get(&player_state.art_url)
+                    .and_then(|art| art.color)
+                    .map(|color| color.hex())
+                    .unwrap_or_default(),
+            ),
         ]
         .into_iter()
         .collect();
 
-        replacements
-            .iter()
-            .fold(self.args.format.clone(), |acc, (key, value)| {
-                acc.replace(&format!("%{key}%"), value)
-            })
+        // with --markup the format may contain markup, like <span foreground='%art-color%'>,
+        // so only the values are escaped
+        fill_placeholders(
+            format,
+            &replacements,
+            |literal| {
+                if self.args.markup {
+                    literal.to_string()
+                } else {
+                    self.output.escape_format(literal)
+                }
+            },
+            |value| self.output.escape(value),
+        )
     }
 
-    fn draw(&self, player_state: &Option<PlayerState>, fields: &mut HashMap<&str, TextEffect>) {
-        let player_state = match player_state {
-            Some(state) => state,
-            None => {
-                println!(
-                    "{}",
-                    self.format_json_output(&self.args.stopped_label, "stopped")
-                );
-                return;
-            }
+    fn draw(&self, players: &[PlayerState], fields: &mut HashMap<String, Fields>, extras: &Extras) {
+        if let Some(reason) = &extras.bus_error {
+            debug!("showing the error label, the bus is unreachable: {reason}");
+            self.print(
+                &self.output.escape(&self.args.error_label),
+                &[String::from("error")],
+                &[],
+            );
+            return;
+        }
+
+        let visible: Vec<&PlayerState> = players.iter().filter(|p| !p.is_stopped()).collect();
+
+        if visible.is_empty() {
+            let classes = players
+                .first()
+                .map(|p| self.get_classes(p))
+                .unwrap_or_else(|| vec![String::from("stopped")]);
+            self.print(&self.output.escape(&self.args.stopped_label), &classes, &[]);
+            return;
+        }
+
+        let format = if self.args.multi_player {
+            &self.args.player_format
+        } else {
+            &self.args.format
         };
 
-        if player_state
-            .playing
-            .clone()
-            .is_some_and(|playback| playback == PlaybackState::Stopped)
-            || player_state.playing.is_none()
+        let text = visible
+            .iter()
+            .enumerate()
+            .filter_map(|(i, player_state)| {
+                fields.get_mut(&player_state.player_id).map(|fields| {
+                    self.populate_using_placeholders(
+                        format,
+                        player_state,
+                        fields,
+                        i + 1,
+                        visible.len(),
+                        extras,
+                    )
+                    .trim()
+                    .to_string()
+                })
+            })
+            .collect::<Vec<String>>()
+            .join(&if self.args.markup {
+                self.args.player_separator.clone()
+            } else {
+                self.output.escape_format(&self.args.player_separator)
+            });
+
+        let mut classes = self.get_classes(visible[0]);
+        if visible.len() > 1 {
+            classes.push(String::from("multiple-players"));
+        }
+        if self.config.lyrics().enabled
+            && extras
+                .lyrics_for(visible[0])
+                .is_some_and(|lyrics| lyrics.lyrics.is_none())
         {
-            println!(
-                "{}",
-                self.format_json_output(&self.args.stopped_label, "stopped")
-            );
-            return;
+            classes.push(String::from("lyrics-missing"));
+        }
+        if let Some(color) = extras
+            .art
+            .get(&visible[0].art_url)
+            .and_then(|art| art.color)
+        {
+            classes.push(color.css_class());
         }
 
-        let output = self.format_json_output(
-            self.populate_using_placeholders(player_state, fields)
-                .trim(),
-            &self.get_class(player_state),
-        );
+        let now_playing = visible
+            .iter()
+            .map(|state| NowPlaying::from(*state).with_art(extras.art.get(&state.art_url)))
+            .collect::<Vec<NowPlaying>>();
+
+        self.print(&text, &classes, &now_playing);
+    }
+}
 
-        println!("{}", output)
+/// Replaces every known `%key%` in a single pass, so placeholders inside a value, like a title
+/// containing `%artist%`, are left alone. The text around them goes through `literal`
+fn fill_placeholders(
+    format: &str,
+    values: &HashMap<&str, String>,
+    literal: impl Fn(&str) -> String,
+    value: impl Fn(&str) -> String,
+) -> String {
+    let mut filled = String::new();
+    let mut text = String::new();
+    let mut rest = format;
+
+    while let Some(start) = rest.find('%') {
+        let after = &rest[start + 1..];
+        let placeholder = after
+            .find('%')
+            .and_then(|end| values.get(&after[..end]).map(|v| (end, v)));
+
+        match placeholder {
+            Some((end, v)) => {
+                text.push_str(&rest[..start]);
+                filled.push_str(&literal(&text));
+                filled.push_str(&value(v));
+                text.clear();
+                rest = &after[end + 1..];
+            }
+            None => {
+                text.push_str(&rest[..=start]);
+                rest = after;
+            }
+        }
     }
+    text.push_str(rest);
+    filled.push_str(&literal(&text));
+
+    filled
 }
 
 impl Runnable for Display {
@@ -389,3 +726,41 @@ impl Runnable for Display {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fill(output: &dyn Output, format: &str, values: &[(&'static str, &str)]) -> String {
+        let values = values.iter().map(|(k, v)| (*k, v.to_string())).collect();
+        fill_placeholders(
+            format,
+            &values,
+            |literal| output.escape_format(literal),
+            |value| output.escape(value),
+        )
+    }
+
+    #[test]
+    fn polybar_values_have_no_stray_percent_signs() {
+        let values = [("artist", "Artist"), ("title", "100% Pure")];
+        assert_eq!(
+            fill(&Polybar, "%artist% - %title%", &values),
+            "Artist - 100%% Pure"
+        );
+        // Polybar's own tags in the format are kept
+        assert_eq!(
+            fill(&Polybar, "%{F#1db954}%artist%%{F-}", &values),
+            "%{F#1db954}Artist%{F-}"
+        );
+    }
+
+    #[test]
+    fn literal_text_is_escaped_but_values_are_not_filled_in_again() {
+        let values = [("artist", "A & B"), ("title", "%artist% <live>")];
+        assert_eq!(
+            fill(&Waybar, "R&B: %artist% - %title% 50%", &values),
+            "R&amp;B: A &amp; B - %artist% &lt;live&gt; 50%"
+        );
+    }
+}
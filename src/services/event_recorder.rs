@@ -0,0 +1,71 @@
+use std::{
+    sync::Arc,
+    thread::{self, JoinHandle},
+};
+
+use log::{error, info};
+
+use crate::{
+    event_bus::{EventBusHandle, EventType},
+    interfaces::recording::{Entry, Recording},
+};
+
+use super::runnable::Runnable;
+
+/// Everything PlayerManager reacts to, which is what a replay feeds it again
+pub const RECORDED_EVENTS: [EventType; 6] = [
+    EventType::PlayerSongChanged,
+    EventType::PlaybackChanged,
+    EventType::Seeked,
+    EventType::Rate,
+    EventType::ControlCommand,
+    EventType::BusStatusChanged,
+];
+
+/// Writes the events PlayerManager reacts to into the `--record` file
+pub struct EventRecorder {
+    event_bus: EventBusHandle,
+    recording: Arc<Recording>,
+}
+
+impl EventRecorder {
+    pub fn new(event_bus: EventBusHandle, recording: Arc<Recording>) -> Self {
+        Self {
+            event_bus,
+            recording,
+        }
+    }
+
+    fn record_event(&self, event_type: EventType) -> Option<JoinHandle<()>> {
+        let Some(rx) = self.event_bus.subscribe(event_type.clone()) else {
+            error!("failed to subscribe to {event_type} listener");
+            return None;
+        };
+
+        let recording = self.recording.clone();
+        Some(thread::spawn(move || {
+            for data in rx {
+                recording.write(Entry::Event {
+                    event_type: event_type.clone(),
+                    data,
+                });
+            }
+        }))
+    }
+}
+
+impl Runnable for EventRecorder {
+    fn run(self: Arc<Self>) -> JoinHandle<()> {
+        thread::spawn(move || {
+            info!("starting EventRecorder thread");
+            let handles: Vec<JoinHandle<()>> = RECORDED_EVENTS
+                .into_iter()
+                .filter_map(|event_type| self.record_event(event_type))
+                .collect();
+            for handle in handles {
+                let _ = handle.join();
+            }
+            info!("EventRecorder thread is stopping");
+        })
+    }
+}
@@ -0,0 +1,145 @@
+use std::{
+    sync::{
+        mpsc::{Receiver, RecvTimeoutError},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use bincode::config;
+use log::{debug, error, info, warn};
+
+use crate::{
+    event_bus::{EventBusHandle, EventType},
+    interfaces::play_store::PlayStore,
+    models::{
+        config::Config, listening_stats::ListeningStats, play_tracker::PlayTracker,
+        player_state::PlayerState,
+    },
+    utils::time::start_of_local_day,
+};
+
+use super::runnable::Runnable;
+
+/// How often to check whether the day rolled over, so today's stats reset at midnight
+const DAY_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Keeps a local history of the tracks that were listened to, see `waybar-module-music history`,
+/// and publishes today's listening stats for the display
+pub struct HistoryRecorder {
+    config: Arc<Config>,
+    event_bus: EventBusHandle,
+}
+
+impl HistoryRecorder {
+    pub fn new(config: Arc<Config>, event_bus: EventBusHandle) -> Self {
+        Self { config, event_bus }
+    }
+
+    fn init_worker(&self) {
+        let store = match PlayStore::history() {
+            Ok(store) => store,
+            Err(err) => {
+                error!("failed to open the listening history: {err}");
+                return;
+            }
+        };
+
+        match self.event_bus.subscribe(EventType::PlayerStateChanged) {
+            Some(rx) => self.listen_for_updates(rx, store),
+            None => error!("failed to subscribe to PlayerStateChanged listener"),
+        }
+    }
+
+    fn todays_stats(store: &PlayStore, day_start: u64) -> ListeningStats {
+        match store.read_all() {
+            Ok(plays) => {
+                ListeningStats::from_plays(plays.iter().filter(|play| play.timestamp >= day_start))
+            }
+            Err(err) => {
+                error!("failed to read the listening history: {err}");
+                ListeningStats::default()
+            }
+        }
+    }
+
+    fn publish_stats(&self, stats: &ListeningStats) {
+        match bincode::encode_to_vec(stats, config::standard()) {
+            Ok(encoded) => self
+                .event_bus
+                .publish(EventType::ListeningStatsChanged, encoded),
+            Err(err) => error!("failed to encode listening stats: {err}"),
+        }
+    }
+
+    fn listen_for_updates(&self, rx: Receiver<Vec<u8>>, store: PlayStore) {
+        let history = self.config.history();
+        let mut tracker = PlayTracker::new(
+            history.min_percent,
+            Duration::from_secs(history.min_seconds),
+        );
+
+        let mut day_start = start_of_local_day(0);
+        let mut stats = HistoryRecorder::todays_stats(&store, day_start);
+        // the display may not be listening yet when we start, so the stats go out with the first update
+        let mut published: Option<ListeningStats> = None;
+
+        loop {
+            match rx.recv_timeout(DAY_CHECK_INTERVAL) {
+                Ok(encoded) => {
+                    match bincode::decode_from_slice::<PlayerState, _>(
+                        &encoded[..],
+                        config::standard(),
+                    ) {
+                        Ok((state, _)) => {
+                            if let Some(play) = tracker.update(&state) {
+                                debug!("recording play of '{} - {}'", play.artist, play.title);
+                                if let Err(err) = store.append(&play) {
+                                    error!("failed to write to the listening history: {err}");
+                                }
+                                if play.timestamp >= day_start {
+                                    stats.add(&play);
+                                }
+                            }
+                        }
+                        Err(err) => warn!("failed to decode message in HistoryRecorder: {err}"),
+                    }
+                }
+                Err(RecvTimeoutError::Timeout) => (),
+                Err(RecvTimeoutError::Disconnected) => {
+                    warn!("HistoryRecorder lost its PlayerStateChanged subscription");
+                    return;
+                }
+            }
+
+            if start_of_local_day(0) != day_start {
+                day_start = start_of_local_day(0);
+                stats = HistoryRecorder::todays_stats(&store, day_start);
+            }
+
+            // the playing track counts as soon as it would be recorded, not only once it ends
+            let mut shown = stats.clone();
+            if let Some(play) = tracker.in_progress() {
+                if play.timestamp >= day_start {
+                    shown.add(&play);
+                }
+            }
+
+            if published.as_ref() != Some(&shown) {
+                self.publish_stats(&shown);
+                published = Some(shown);
+            }
+        }
+    }
+}
+
+impl Runnable for HistoryRecorder {
+    fn run(self: Arc<Self>) -> JoinHandle<()> {
+        thread::spawn(move || {
+            info!("starting HistoryRecorder thread");
+            self.init_worker();
+            info!("HistoryRecorder thread is stopping");
+        })
+    }
+}
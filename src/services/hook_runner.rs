@@ -0,0 +1,241 @@
+use std::{
+    collections::HashMap,
+    process::{Child, Command, Stdio},
+    sync::{
+        mpsc::{Receiver, RecvTimeoutError},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use bincode::config;
+use log::{debug, error, info, warn};
+
+use crate::{
+    event_bus::{EventBusHandle, EventType},
+    models::{
+        config::{Config, Hooks},
+        playback_state::PlaybackState,
+        player_state::PlayerState,
+    },
+};
+
+use super::runnable::Runnable;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum HookKind {
+    Track,
+    State,
+    Player,
+}
+
+impl HookKind {
+    fn command(self, hooks: &Hooks) -> Option<&String> {
+        match self {
+            HookKind::Track => hooks.on_track_change.as_ref(),
+            HookKind::State => hooks.on_state_change.as_ref(),
+            HookKind::Player => hooks.on_player_change.as_ref(),
+        }
+    }
+}
+
+/// Runs the user's hook commands from config.toml when the displayed player changes
+pub struct HookRunner {
+    config: Arc<Config>,
+    event_bus: EventBusHandle,
+}
+
+impl HookRunner {
+    pub fn new(config: Arc<Config>, event_bus: EventBusHandle) -> Self {
+        Self { config, event_bus }
+    }
+
+    fn init_worker(&self) {
+        match self.event_bus.subscribe(EventType::PlayerStateChanged) {
+            Some(rx) => self.listen_for_updates(rx),
+            None => error!("failed to subscribe to PlayerStateChanged listener"),
+        }
+    }
+
+    /// Which hooks should run when going from `previous` to `current`
+    fn changed_hooks(previous: Option<&PlayerState>, current: &PlayerState) -> Vec<HookKind> {
+        let Some(previous) = previous else {
+            return vec![HookKind::Track, HookKind::State, HookKind::Player];
+        };
+
+        let mut changed = vec![];
+        if previous.player_id != current.player_id
+            || previous.artist != current.artist
+            || previous.title != current.title
+            || previous.album != current.album
+        {
+            changed.push(HookKind::Track);
+        }
+        if previous.playing != current.playing {
+            changed.push(HookKind::State);
+        }
+        if previous.player_id != current.player_id {
+            changed.push(HookKind::Player);
+        }
+        changed
+    }
+
+    fn listen_for_updates(&self, rx: Receiver<Vec<u8>>) {
+        let hooks = self.config.hooks();
+        let debounce = Duration::from_millis(hooks.debounce_ms);
+        let timeout = Duration::from_millis(hooks.timeout_ms);
+
+        // the last state we ran hooks for, and the state waiting for the debounce to pass
+        let mut last_handled: Option<PlayerState> = None;
+        let mut pending: Option<(PlayerState, Instant)> = None;
+        let mut running: HashMap<HookKind, (Child, Instant)> = HashMap::new();
+
+        loop {
+            let deadline = pending
+                .as_ref()
+                .map(|(_, changed_at)| *changed_at + debounce)
+                .into_iter()
+                .chain(running.values().map(|(_, started)| *started + timeout))
+                .min();
+
+            let msg = match deadline {
+                Some(deadline) => {
+                    rx.recv_timeout(deadline.saturating_duration_since(Instant::now()))
+                }
+                None => rx.recv().map_err(|_| RecvTimeoutError::Disconnected),
+            };
+
+            match msg {
+                Ok(encoded) => {
+                    match bincode::decode_from_slice::<PlayerState, _>(
+                        &encoded[..],
+                        config::standard(),
+                    ) {
+                        Ok((state, _)) => {
+                            // position updates arrive constantly, so only restart the debounce when a hook would run
+                            let compared_to =
+                                pending.as_ref().map(|(p, _)| p).or(last_handled.as_ref());
+                            if !HookRunner::changed_hooks(compared_to, &state).is_empty() {
+                                pending = Some((state, Instant::now()));
+                            }
+                        }
+                        Err(err) => warn!("failed to decode message in HookRunner: {err}"),
+                    }
+                }
+                Err(RecvTimeoutError::Timeout) => (),
+                Err(RecvTimeoutError::Disconnected) => {
+                    warn!("HookRunner lost its PlayerStateChanged subscription");
+                    return;
+                }
+            }
+
+            if let Some((state, changed_at)) = pending.take() {
+                if changed_at.elapsed() >= debounce {
+                    for kind in HookRunner::changed_hooks(last_handled.as_ref(), &state) {
+                        if let Some(command) = kind.command(hooks) {
+                            self.run_hook(kind, command, &state, &mut running);
+                        }
+                    }
+                    last_handled = Some(state);
+                } else {
+                    pending = Some((state, changed_at));
+                }
+            }
+
+            HookRunner::reap_hooks(&mut running, timeout);
+        }
+    }
+
+    fn run_hook(
+        &self,
+        kind: HookKind,
+        command: &str,
+        state: &PlayerState,
+        running: &mut HashMap<HookKind, (Child, Instant)>,
+    ) {
+        // never let the same hook pile up, the newer state is the one that matters
+        if let Some((mut child, _)) = running.remove(&kind) {
+            if let Ok(None) = child.try_wait() {
+                warn!(
+                    "{kind:?} change hook is still running, killing it before starting a new one"
+                );
+                let _ = child.kill();
+                let _ = child.wait();
+            }
+        }
+
+        debug!("running {kind:?} change hook: {command}");
+        match Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .envs(HookRunner::environment(state))
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+        {
+            Ok(child) => {
+                running.insert(kind, (child, Instant::now()));
+            }
+            Err(err) => error!("failed to run {kind:?} change hook: {err}"),
+        }
+    }
+
+    /// Clean up finished hooks, and kill the ones that have run for too long
+    fn reap_hooks(running: &mut HashMap<HookKind, (Child, Instant)>, timeout: Duration) {
+        running.retain(|kind, (child, started)| match child.try_wait() {
+            Ok(Some(status)) => {
+                if !status.success() {
+                    warn!("{kind:?} change hook exited with {status}");
+                }
+                false
+            }
+            Ok(None) if started.elapsed() >= timeout => {
+                warn!("{kind:?} change hook timed out after {timeout:?}, killing it");
+                let _ = child.kill();
+                let _ = child.wait();
+                false
+            }
+            Ok(None) => true,
+            Err(err) => {
+                error!("failed to check on {kind:?} change hook: {err}");
+                false
+            }
+        });
+    }
+
+    fn environment(state: &PlayerState) -> Vec<(&'static str, String)> {
+        vec![
+            ("WAYBAR_MUSIC_PLAYER", state.player_name.clone()),
+            ("WAYBAR_MUSIC_PLAYER_ID", state.player_id.clone()),
+            ("WAYBAR_MUSIC_ARTIST", state.artist.clone()),
+            ("WAYBAR_MUSIC_TITLE", state.title.clone()),
+            ("WAYBAR_MUSIC_ALBUM", state.album.clone()),
+            // MPRIS gives us microseconds, but seconds are far more useful in a script
+            (
+                "WAYBAR_MUSIC_LENGTH",
+                (state.length / 1000 / 1000).to_string(),
+            ),
+            ("WAYBAR_MUSIC_ART_URL", state.art_url.clone()),
+            (
+                "WAYBAR_MUSIC_STATUS",
+                state
+                    .playing
+                    .clone()
+                    .unwrap_or(PlaybackState::Stopped)
+                    .to_string(),
+            ),
+        ]
+    }
+}
+
+impl Runnable for HookRunner {
+    fn run(self: Arc<Self>) -> JoinHandle<()> {
+        thread::spawn(move || {
+            info!("starting HookRunner thread");
+            self.init_worker();
+            info!("HookRunner thread is stopping");
+        })
+    }
+}
@@ -0,0 +1,85 @@
+use std::{
+    sync::{mpsc::Receiver, Arc},
+    thread::{self, JoinHandle},
+};
+
+use bincode::config;
+use log::{debug, error, info, warn};
+
+use crate::{
+    event_bus::{EventBusHandle, EventType},
+    interfaces::lyrics_finder::LyricsFinder,
+    models::{config::Config, player_state::PlayerState, track_lyrics::TrackLyrics},
+};
+
+use super::runnable::Runnable;
+
+/// Looks up lyrics whenever the displayed track changes, off the display thread
+pub struct LyricsLoader {
+    config: Arc<Config>,
+    event_bus: EventBusHandle,
+}
+
+impl LyricsLoader {
+    pub fn new(config: Arc<Config>, event_bus: EventBusHandle) -> Self {
+        Self { config, event_bus }
+    }
+
+    fn init_worker(&self) {
+        match self.event_bus.subscribe(EventType::PlayerStateChanged) {
+            Some(rx) => self.listen_for_updates(rx),
+            None => error!("failed to subscribe to PlayerStateChanged listener"),
+        }
+    }
+
+    fn listen_for_updates(&self, rx: Receiver<Vec<u8>>) {
+        let finder = LyricsFinder::new(&self.config.lyrics().directories);
+        let mut last_track: Option<TrackLyrics> = None;
+
+        for encoded in rx {
+            let state = match bincode::decode_from_slice::<PlayerState, _>(
+                &encoded[..],
+                config::standard(),
+            ) {
+                Ok((state, _)) => state,
+                Err(err) => {
+                    warn!("failed to decode message in LyricsLoader: {err}");
+                    continue;
+                }
+            };
+
+            if !state.has_data() || last_track.as_ref().is_some_and(|t| t.is_for(&state)) {
+                continue;
+            }
+
+            let lyrics = finder.find(&state);
+            if lyrics.is_none() {
+                debug!("no lyrics found for '{} - {}'", state.artist, state.title);
+            }
+
+            let track = TrackLyrics {
+                player_id: state.player_id,
+                artist: state.artist,
+                title: state.title,
+                lyrics,
+            };
+            match bincode::encode_to_vec(&track, config::standard()) {
+                Ok(encoded) => self.event_bus.publish(EventType::LyricsLoaded, encoded),
+                Err(err) => error!("failed to encode lyrics: {err}"),
+            }
+            last_track = Some(track);
+        }
+
+        warn!("LyricsLoader lost its PlayerStateChanged subscription");
+    }
+}
+
+impl Runnable for LyricsLoader {
+    fn run(self: Arc<Self>) -> JoinHandle<()> {
+        thread::spawn(move || {
+            info!("starting LyricsLoader thread");
+            self.init_worker();
+            info!("LyricsLoader thread is stopping");
+        })
+    }
+}
@@ -1,4 +1,19 @@
+pub mod art_fetcher;
+pub mod click_listener;
+pub mod control_listener;
+pub mod daemon_client;
+pub mod daemon_server;
 pub mod dbus_monitor;
+pub mod dbus_service;
 pub mod display;
+pub mod event_recorder;
+pub mod history_recorder;
+pub mod hook_runner;
+pub mod lyrics_loader;
+pub mod mpris_proxy;
+pub mod notifier;
 pub mod player_manager;
 pub mod runnable;
+pub mod scrobbler;
+pub mod single_owner;
+pub mod state_exporter;
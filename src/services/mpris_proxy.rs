@@ -0,0 +1,314 @@
+use std::{
+    collections::HashMap,
+    error::Error,
+    ffi::CString,
+    sync::Arc,
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use bincode::config;
+use dbus::{
+    arg::PropMap,
+    blocking::{
+        stdintf::org_freedesktop_dbus::{PropertiesPropertiesChanged, RequestNameReply},
+        Connection,
+    },
+    channel::Sender,
+    message::{MessageType, SignalArgs},
+    strings::ErrorName,
+    Message,
+};
+use log::{debug, info, warn};
+
+use crate::{
+    event_bus::{EventBusHandle, EventType},
+    interfaces::bus::{self, Bus},
+    models::player_state::PlayerState,
+};
+
+use super::runnable::Runnable;
+
+/// The name media keys can target, e.g. `playerctl -p waybar_active play-pause`
+pub const BUS_NAME: &str = "org.mpris.MediaPlayer2.waybar_active";
+const OBJECT_PATH: &str = "/org/mpris/MediaPlayer2";
+const INTERFACES: [&str; 2] = ["org.mpris.MediaPlayer2", "org.mpris.MediaPlayer2.Player"];
+
+/// How long a player gets to answer a forwarded call before the caller gets an error
+const REPLY_TIMEOUT: Duration = Duration::from_secs(25);
+
+/// Re-exposes the displayed player as a stable MPRIS player, like playerctld does.
+/// Calls are forwarded to the active player as they are, and its signals are re-emitted as ours
+pub struct MprisProxy {
+    event_bus: EventBusHandle,
+    bus: Bus,
+}
+
+/// Something we're waiting on the active player to answer
+enum Pending {
+    /// A call we forwarded, which its caller is waiting on
+    Call(Message, Instant),
+    /// All properties of an interface, to announce after switching players
+    Announce(String, Instant),
+}
+
+impl Pending {
+    fn sent_at(&self) -> Instant {
+        match self {
+            Pending::Call(_, sent_at) | Pending::Announce(_, sent_at) => *sent_at,
+        }
+    }
+}
+
+impl MprisProxy {
+    pub fn new(event_bus: EventBusHandle, bus: Bus) -> Self {
+        Self { event_bus, bus }
+    }
+
+    fn error_reply(call: &Message, name: &str, text: &str) -> Message {
+        let name =
+            ErrorName::new(name).unwrap_or_else(|_| "org.freedesktop.DBus.Error.Failed".into());
+        let text = CString::new(text).unwrap_or_default();
+        call.error(&name, &text)
+    }
+
+    /// Sends `call` on to the active player, or answers it right away if it can't be
+    fn forward_call(
+        conn: &Connection,
+        call: Message,
+        active: Option<&str>,
+        pending: &mut HashMap<u32, Pending>,
+    ) {
+        if call.path().as_deref() != Some(OBJECT_PATH) {
+            let reply = MprisProxy::error_reply(
+                &call,
+                "org.freedesktop.DBus.Error.UnknownObject",
+                "only /org/mpris/MediaPlayer2 is served",
+            );
+            let _ = conn.send(reply);
+            return;
+        }
+
+        let Some(active) = active else {
+            let reply = MprisProxy::error_reply(
+                &call,
+                "org.freedesktop.DBus.Error.ServiceUnknown",
+                "no active player",
+            );
+            let _ = conn.send(reply);
+            return;
+        };
+
+        let (Some(interface), Some(member)) = (call.interface(), call.member()) else {
+            let reply = MprisProxy::error_reply(
+                &call,
+                "org.freedesktop.DBus.Error.UnknownMethod",
+                "missing interface or member",
+            );
+            let _ = conn.send(reply);
+            return;
+        };
+
+        let mut forwarded =
+            match Message::new_method_call(active, OBJECT_PATH, &*interface, &*member) {
+                Ok(msg) => msg,
+                Err(err) => {
+                    warn!("failed to forward '{interface}.{member}' to '{active}': {err}");
+                    let reply = MprisProxy::error_reply(
+                        &call,
+                        "org.freedesktop.DBus.Error.Failed",
+                        &format!("failed to forward the call: {err}"),
+                    );
+                    let _ = conn.send(reply);
+                    return;
+                }
+            };
+        forwarded.append_items(&call.get_items());
+
+        debug!("forwarding '{interface}.{member}' to '{active}'");
+        match conn.send(forwarded) {
+            Ok(serial) => {
+                pending.insert(serial, Pending::Call(call, Instant::now()));
+            }
+            Err(()) => {
+                warn!("failed to forward '{interface}.{member}' to '{active}'");
+                let reply = MprisProxy::error_reply(
+                    &call,
+                    "org.freedesktop.DBus.Error.Failed",
+                    "failed to forward the call",
+                );
+                let _ = conn.send(reply);
+            }
+        }
+    }
+
+    /// Passes the active player's answer back to whoever was waiting on it
+    fn handle_reply(conn: &Connection, mut reply: Message, pending: Pending) {
+        match pending {
+            Pending::Call(call, _) => {
+                let answer = match reply.as_result() {
+                    Ok(reply) => {
+                        let mut answer = call.method_return();
+                        answer.append_items(&reply.get_items());
+                        answer
+                    }
+                    Err(err) => MprisProxy::error_reply(
+                        &call,
+                        err.name().unwrap_or("org.freedesktop.DBus.Error.Failed"),
+                        err.message().unwrap_or_default(),
+                    ),
+                };
+                let _ = conn.send(answer);
+            }
+            Pending::Announce(interface, _) => match reply.read1::<PropMap>() {
+                Ok(properties) => MprisProxy::announce(conn, interface, properties),
+                Err(err) => {
+                    debug!("active player didn't return properties for '{interface}': {err}")
+                }
+            },
+        }
+    }
+
+    fn announce(conn: &Connection, interface: String, properties: PropMap) {
+        let signal = PropertiesPropertiesChanged {
+            interface_name: interface,
+            changed_properties: properties,
+            invalidated_properties: vec![],
+        };
+        if conn
+            .send(signal.to_emit_message(&OBJECT_PATH.into()))
+            .is_err()
+        {
+            warn!("failed to send PropertiesChanged signal");
+        }
+    }
+
+    /// Re-emits a signal of the active player as if it were ours
+    fn mirror_signal(conn: &Connection, signal: &Message) {
+        let (Some(interface), Some(member)) = (signal.interface(), signal.member()) else {
+            return;
+        };
+
+        match Message::new_signal(OBJECT_PATH, &*interface, &*member) {
+            Ok(mut mirrored) => {
+                mirrored.append_items(&signal.get_items());
+                if conn.send(mirrored).is_err() {
+                    warn!("failed to mirror '{interface}.{member}' signal");
+                }
+            }
+            Err(err) => warn!("failed to mirror '{interface}.{member}' signal: {err}"),
+        }
+    }
+
+    /// Asks the new active player for all of its properties, so clients see the switch
+    fn request_announcement(conn: &Connection, active: &str, pending: &mut HashMap<u32, Pending>) {
+        for interface in INTERFACES {
+            let Ok(call) = Message::new_method_call(
+                active,
+                OBJECT_PATH,
+                "org.freedesktop.DBus.Properties",
+                "GetAll",
+            ) else {
+                continue;
+            };
+
+            if let Ok(serial) = conn.send(call.append1(interface)) {
+                pending.insert(
+                    serial,
+                    Pending::Announce(interface.to_string(), Instant::now()),
+                );
+            }
+        }
+    }
+
+    /// Players that never answer shouldn't keep their callers waiting forever
+    fn expire_pending(conn: &Connection, pending: &mut HashMap<u32, Pending>) {
+        pending.retain(|_, p| {
+            if p.sent_at().elapsed() < REPLY_TIMEOUT {
+                return true;
+            }
+
+            if let Pending::Call(call, _) = p {
+                let reply = MprisProxy::error_reply(
+                    call,
+                    "org.freedesktop.DBus.Error.NoReply",
+                    "the active player did not reply",
+                );
+                let _ = conn.send(reply);
+            }
+            false
+        });
+    }
+
+    fn serve(&self) -> Result<(), Box<dyn Error>> {
+        let conn = self.bus.connect()?;
+        if conn.request_name(BUS_NAME, false, false, true)? != RequestNameReply::PrimaryOwner {
+            info!("'{BUS_NAME}' is taken by another instance, not serving it");
+            return Ok(());
+        }
+        // subscribing only once the name is ours, so a second instance leaves no idle subscriber behind
+        let Some(rx) = self.event_bus.subscribe(EventType::PlayerStateChanged) else {
+            return Err("failed to subscribe to PlayerStateChanged listener".into());
+        };
+        conn.add_match_no_cb(&format!("type='signal',path='{OBJECT_PATH}'"))?;
+        info!("proxying the active player as '{BUS_NAME}'");
+
+        let mut active: Option<String> = None;
+        let mut pending: HashMap<u32, Pending> = HashMap::new();
+
+        loop {
+            if conn
+                .channel()
+                .read_write(Some(Duration::from_millis(100)))
+                .is_err()
+            {
+                return Err("lost the D-Bus connection".into());
+            }
+
+            while let Some(msg) = conn.channel().pop_message() {
+                match msg.msg_type() {
+                    MessageType::MethodCall => {
+                        MprisProxy::forward_call(&conn, msg, active.as_deref(), &mut pending);
+                    }
+                    MessageType::MethodReturn | MessageType::Error => {
+                        if let Some(p) = msg.get_reply_serial().and_then(|s| pending.remove(&s)) {
+                            MprisProxy::handle_reply(&conn, msg, p);
+                        }
+                    }
+                    MessageType::Signal => {
+                        let sender = msg.sender();
+                        if active.is_some() && sender.as_deref() == active.as_deref() {
+                            MprisProxy::mirror_signal(&conn, &msg);
+                        }
+                    }
+                }
+            }
+
+            for encoded in rx.try_iter() {
+                match bincode::decode_from_slice::<PlayerState, _>(&encoded[..], config::standard())
+                {
+                    Ok((state, _)) => {
+                        if active.as_ref() != Some(&state.player_id) {
+                            debug!("now proxying '{}'", state.player_id);
+                            MprisProxy::request_announcement(&conn, &state.player_id, &mut pending);
+                            active = Some(state.player_id);
+                        }
+                    }
+                    Err(err) => warn!("failed to decode message in MprisProxy: {err}"),
+                }
+            }
+
+            MprisProxy::expire_pending(&conn, &mut pending);
+        }
+    }
+}
+
+impl Runnable for MprisProxy {
+    fn run(self: Arc<Self>) -> JoinHandle<()> {
+        thread::spawn(move || {
+            info!("starting MprisProxy thread");
+            bus::supervise("MprisProxy", || self.serve());
+            info!("MprisProxy thread is stopping");
+        })
+    }
+}
@@ -0,0 +1,168 @@
+use std::{
+    sync::{
+        mpsc::{Receiver, RecvTimeoutError},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use bincode::config;
+use log::{debug, error, info, warn};
+
+use crate::{
+    event_bus::{EventBusHandle, EventType},
+    interfaces::dbus_client::DBusClient,
+    models::{config::Config, playback_state::PlaybackState, player_state::PlayerState},
+    utils::template,
+};
+
+use super::runnable::Runnable;
+
+/// Sends a desktop notification when the displayed track changes
+pub struct Notifier {
+    config: Arc<Config>,
+    event_bus: EventBusHandle,
+    dbus_client: Arc<DBusClient>,
+}
+
+impl Notifier {
+    pub fn new(
+        config: Arc<Config>,
+        event_bus: EventBusHandle,
+        dbus_client: Arc<DBusClient>,
+    ) -> Self {
+        Self {
+            config,
+            event_bus,
+            dbus_client,
+        }
+    }
+
+    fn init_worker(&self) {
+        match self.event_bus.subscribe(EventType::PlayerStateChanged) {
+            Some(rx) => self.listen_for_updates(rx),
+            None => error!("failed to subscribe to PlayerStateChanged listener"),
+        }
+    }
+
+    fn is_same_track(a: &PlayerState, b: &PlayerState) -> bool {
+        a.player_id == b.player_id
+            && a.artist == b.artist
+            && a.title == b.title
+            && a.album == b.album
+    }
+
+    fn listen_for_updates(&self, rx: Receiver<Vec<u8>>) {
+        let debounce = Duration::from_millis(self.config.notifications().debounce_ms);
+
+        let mut notification_id: u32 = 0;
+        let mut last_notified: Option<PlayerState> = None;
+        let mut pending: Option<(PlayerState, Instant)> = None;
+
+        loop {
+            let msg = match &pending {
+                Some((_, changed_at)) => rx.recv_timeout(
+                    (*changed_at + debounce).saturating_duration_since(Instant::now()),
+                ),
+                None => rx.recv().map_err(|_| RecvTimeoutError::Disconnected),
+            };
+
+            match msg {
+                Ok(encoded) => {
+                    match bincode::decode_from_slice::<PlayerState, _>(
+                        &encoded[..],
+                        config::standard(),
+                    ) {
+                        Ok((state, _)) => {
+                            if state.playing != Some(PlaybackState::Playing) {
+                                // a track that was paused or stopped before the debounce passed was most likely skipped
+                                pending = None;
+                                continue;
+                            }
+
+                            let compared_to =
+                                pending.as_ref().map(|(p, _)| p).or(last_notified.as_ref());
+                            if !compared_to.is_some_and(|p| Notifier::is_same_track(p, &state)) {
+                                pending = Some((state, Instant::now()));
+                            }
+                        }
+                        Err(err) => warn!("failed to decode message in Notifier: {err}"),
+                    }
+                }
+                Err(RecvTimeoutError::Timeout) => (),
+                Err(RecvTimeoutError::Disconnected) => {
+                    warn!("Notifier lost its PlayerStateChanged subscription");
+                    return;
+                }
+            }
+
+            if let Some((state, changed_at)) = pending.take() {
+                if changed_at.elapsed() < debounce {
+                    pending = Some((state, changed_at));
+                    continue;
+                }
+
+                if last_notified
+                    .as_ref()
+                    .is_some_and(|p| Notifier::is_same_track(p, &state))
+                {
+                    continue;
+                }
+
+                if self
+                    .config
+                    .notifications()
+                    .should_notify_for(&state.player_name)
+                {
+                    notification_id = self.notify(notification_id, &state);
+                } else {
+                    debug!("not notifying for '{}', filtered out", state.player_name);
+                }
+                last_notified = Some(state);
+            }
+        }
+    }
+
+    /// Returns the ID of the notification, so the next one can replace it
+    fn notify(&self, replaces_id: u32, state: &PlayerState) -> u32 {
+        let notifications = self.config.notifications();
+        let summary = template::render(&notifications.summary, state);
+        let body = Notifier::escape_markup(&template::render(&notifications.body, state));
+        let image_path = state
+            .art_url
+            .starts_with("file://")
+            .then_some(state.art_url.as_str());
+
+        match self.dbus_client.notify(
+            replaces_id,
+            &summary,
+            &body,
+            image_path,
+            notifications.expire_timeout_ms,
+        ) {
+            Ok(id) => id,
+            Err(err) => {
+                error!("failed to send notification: {err}");
+                replaces_id
+            }
+        }
+    }
+
+    /// Notification bodies may contain markup, so we escape the characters that would be mistaken for it
+    fn escape_markup(text: &str) -> String {
+        text.replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+    }
+}
+
+impl Runnable for Notifier {
+    fn run(self: Arc<Self>) -> JoinHandle<()> {
+        thread::spawn(move || {
+            info!("starting Notifier thread");
+            self.init_worker();
+            info!("Notifier thread is stopping");
+        })
+    }
+}
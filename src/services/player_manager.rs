@@ -1,24 +1,31 @@
-use bincode::config;
+use bincode::{config, error::DecodeError};
 use log::{debug, error, info, warn};
 
 use crate::{
     event_bus::{EventBusHandle, EventType},
-    interfaces::dbus_client::DBusClient,
+    interfaces::{
+        player_queries::PlayerQueries,
+        recording::{Entry as RecordEntry, Record},
+    },
     models::{
+        args::Args, bus_status::BusStatus, control_command::ControlCommand,
         mpris_identity::MprisIdentity, mpris_metadata::MprisMetadata,
         mpris_playback::MprisPlayback, mpris_rate::MprisRate, mpris_seeked::MprisSeeked,
-        player_client::PlayerClient, player_state::PlayerState, player_timer::PlayerTimer,
+        playback_state::PlaybackState, player_client::PlayerClient, player_state::PlayerState,
+        player_timer::PlayerTimer,
     },
+    selection::{self, policy::SelectionPolicy},
     services::runnable::Runnable,
 };
 use std::{
     collections::{hash_map::Entry, HashMap},
+    mem,
     sync::{
         mpsc::{self, Receiver, Sender},
         Arc,
     },
     thread::{self, JoinHandle},
-    time::{Duration, Instant},
+    time::Duration,
 };
 
 #[derive(Debug, Clone)]
@@ -29,21 +36,137 @@ enum PlayerManagerMessage {
     Rate(MprisRate),
     Identity(MprisIdentity),
     PlayerTick((String, u128)),
+    Command(ControlCommand),
+    BusStatus(BusStatus),
+}
+
+/// State owned by the event handling thread
+struct PlayerManagerState {
+    players: HashMap<String, PlayerClient>,
+    /// The player currently displayed
+    active: Option<String>,
+    /// Player pinned by the user. While set, events from other players won't change the displayed player
+    pinned: Option<String>,
+    /// Player chosen with `player next` / `player prev`, displayed until it stops or another player starts playing
+    chosen: Option<String>,
+    policy: Box<dyn SelectionPolicy>,
+}
+
+impl PlayerManagerState {
+    fn new(policy: Box<dyn SelectionPolicy>) -> Self {
+        Self {
+            players: HashMap::new(),
+            active: None,
+            pinned: None,
+            chosen: None,
+            policy,
+        }
+    }
+
+    /// The player to display: the pinned one, else the one chosen by hand while it lasts,
+    /// else whichever the selection policy prefers
+    fn choose_active(&mut self) -> Option<String> {
+        if self
+            .chosen
+            .as_ref()
+            .is_some_and(|id| self.players.get(id).is_none_or(|p| p.is_stopped()))
+        {
+            self.chosen = None;
+        }
+
+        self.pinned
+            .clone()
+            .or_else(|| self.chosen.clone())
+            .or_else(|| self.policy.select(&self.players, self.active.as_deref()))
+    }
+
+    /// Display the player `step` positions away from the active one, ordered by name.
+    /// If a player is pinned, the pin moves along with it
+    fn cycle_active_player(&mut self, step: isize) {
+        let mut players: Vec<&PlayerClient> = self.players.values().collect();
+        if players.is_empty() {
+            return;
+        }
+        players.sort_by_key(|p| (p.name().to_lowercase(), p.get_id()));
+
+        let current = self
+            .active
+            .as_ref()
+            .and_then(|id| players.iter().position(|p| &p.get_id() == id))
+            .unwrap_or(0);
+        let next = (current as isize + step).rem_euclid(players.len() as isize) as usize;
+        let id = players[next].get_id();
+
+        if self.pinned.is_some() {
+            self.pinned = Some(id.clone());
+        } else {
+            self.chosen = Some(id.clone());
+        }
+        self.active = Some(id);
+    }
+
+    /// Swaps the known players for the ones found on the bus. Unique names change when the bus
+    /// restarts, so a pin moves on to the player with the same name, or goes if there's none
+    fn replace_players(&mut self, players: HashMap<String, PlayerClient>) {
+        let previous = mem::replace(&mut self.players, players);
+
+        let Some(pinned) = self.pinned.take() else {
+            return;
+        };
+        // the same unique name may well belong to another player now
+        self.pinned = match previous.get(&pinned) {
+            Some(old) => self
+                .players
+                .get(&pinned)
+                .filter(|p| p.name() == old.name())
+                .or_else(|| self.players.values().find(|p| p.name() == old.name()))
+                .map(|p| p.get_id()),
+            None => self.players.contains_key(&pinned).then_some(pinned),
+        };
+
+        if self.pinned.is_none() {
+            info!("the pinned player is gone, unpinning");
+        }
+    }
+
+    /// A player that starts playing takes over from the one chosen by hand
+    fn update_playback(&mut self, mpris_playback: MprisPlayback) {
+        let id = mpris_playback.player_id.clone();
+        let Some(player) = self.players.get_mut(&id) else {
+            return;
+        };
+
+        let started = !player.playing() && mpris_playback.is_playing();
+        player.update_playback_state(mpris_playback);
+        if started && self.chosen.as_ref().is_some_and(|chosen| *chosen != id) {
+            self.chosen = None;
+        }
+    }
 }
 
 pub struct PlayerManager {
-    dbus_client: Arc<DBusClient>,
+    args: Arc<Args>,
+    dbus_client: Arc<dyn PlayerQueries>,
     event_bus: EventBusHandle,
 }
 
 impl PlayerManager {
-    pub fn new(event_bus: EventBusHandle, dbus_client: Arc<DBusClient>) -> Self {
+    pub fn new(
+        args: Arc<Args>,
+        event_bus: EventBusHandle,
+        dbus_client: Arc<dyn PlayerQueries>,
+    ) -> Self {
         Self {
+            args,
             dbus_client,
             event_bus,
         }
     }
 
+    fn init_policy(&self) -> Box<dyn SelectionPolicy> {
+        selection::from_args(&self.args)
+    }
+
     fn init_worker(self: Arc<Self>) {
         let (timer_tx, timer_rx) = mpsc::channel();
         let (tx, rx) = mpsc::channel();
@@ -67,6 +190,16 @@ impl PlayerManager {
         );
         self.subscribe_to_event(EventType::Seeked, tx.clone(), PlayerManagerMessage::Seeked);
         self.subscribe_to_event(EventType::Rate, tx.clone(), PlayerManagerMessage::Rate);
+        self.subscribe_to_event(
+            EventType::ControlCommand,
+            tx.clone(),
+            PlayerManagerMessage::Command,
+        );
+        self.subscribe_to_event(
+            EventType::BusStatusChanged,
+            tx.clone(),
+            PlayerManagerMessage::BusStatus,
+        );
 
         self.handle_events(rx, timer_tx);
     }
@@ -141,6 +274,10 @@ impl PlayerManager {
                             }
                         }
                     }
+                    // the players are gone along with the bus, and come back as new players
+                    PlayerManagerMessage::BusStatus(BusStatus::Disconnected(_)) => {
+                        players.clear();
+                    }
                     // we don't care about any other events
                     _ => continue,
                 }
@@ -206,7 +343,7 @@ impl PlayerManager {
         rx: Receiver<PlayerManagerMessage>,
         timer_tx: Sender<PlayerManagerMessage>,
     ) {
-        let mut players: HashMap<String, PlayerClient> = HashMap::new();
+        let mut state = PlayerManagerState::new(self.init_policy());
         loop {
             let msg: PlayerManagerMessage = match rx.recv() {
                 Ok(msg) => msg,
@@ -216,57 +353,77 @@ impl PlayerManager {
                 }
             };
 
-            match msg.clone() {
-                PlayerManagerMessage::Metadata(mpris_metadata) => {
-                    self.handle_metadata_event(&mut players, mpris_metadata);
+            self.handle_message(&mut state, msg, &timer_tx);
+        }
+    }
+
+    fn handle_message(
+        &self,
+        state: &mut PlayerManagerState,
+        msg: PlayerManagerMessage,
+        timer_tx: &Sender<PlayerManagerMessage>,
+    ) {
+        match msg.clone() {
+            PlayerManagerMessage::Metadata(mpris_metadata) => {
+                self.handle_metadata_event(state, mpris_metadata);
+            }
+            PlayerManagerMessage::PlaybackState(mpris_playback) => {
+                if let Err(err) = timer_tx.send(msg) {
+                    warn!("PlayerManager: failed to re-send message to timer thread! {err}");
                 }
-                PlayerManagerMessage::PlaybackState(mpris_playback) => {
-                    if let Err(err) = timer_tx.send(msg) {
-                        warn!("PlayerManager: failed to re-send message to timer thread! {err}");
-                    }
-                    self.handle_playback_event(&mut players, mpris_playback)
+                self.handle_playback_event(state, mpris_playback)
+            }
+            PlayerManagerMessage::Seeked(mpris_seeked) => {
+                if let Err(err) = timer_tx.send(msg) {
+                    warn!("PlayerManager: failed to re-send message to timer thread! {err}");
                 }
-                PlayerManagerMessage::Seeked(mpris_seeked) => {
-                    if let Err(err) = timer_tx.send(msg) {
-                        warn!("PlayerManager: failed to re-send message to timer thread! {err}");
-                    }
-                    self.handle_seeked_event(&mut players, mpris_seeked)
+                self.handle_seeked_event(state, mpris_seeked)
+            }
+            PlayerManagerMessage::Rate(_) => {
+                if let Err(err) = timer_tx.send(msg) {
+                    warn!("PlayerManager: failed to re-send message to timer thread! {err}");
                 }
-                PlayerManagerMessage::Rate(_) => {
-                    if let Err(err) = timer_tx.send(msg) {
-                        warn!("PlayerManager: failed to re-send message to timer thread! {err}");
-                    }
+            }
+            PlayerManagerMessage::Identity(mpris_identity) => {
+                if let Some(p) = state.players.get_mut(&mpris_identity.player_id) {
+                    p.set_name(mpris_identity.identity);
                 }
-                PlayerManagerMessage::Identity(mpris_identity) => {
-                    if let Some(p) = players.get_mut(&mpris_identity.player_id) {
-                        p.set_name(mpris_identity.identity);
+            }
+            PlayerManagerMessage::PlayerTick((id, position)) => {
+                if let Some(p) = state.players.get_mut(&id) {
+                    if p.playing() {
+                        p.update_position(position);
                     }
                 }
-                PlayerManagerMessage::PlayerTick((id, position)) => {
-                    if let Some(p) = players.get_mut(&id) {
-                        if p.playing() {
-                            p.update_position(position);
-                        }
-                    }
 
-                    if let Some(p) = players.get(&id) {
-                        self.publish_player_state(p, &players);
-                    } else {
-                        warn!("PlayerTick event: tried to get player '{id}', but no such player exists");
-                    }
+                if let Some(p) = state.players.get(&id) {
+                    self.publish_player_state(p, state);
+                } else {
+                    warn!(
+                        "PlayerTick event: tried to get player '{id}', but no such player exists"
+                    );
                 }
-            };
+            }
+            PlayerManagerMessage::Command(command) => {
+                self.handle_control_command(state, command);
+            }
+            PlayerManagerMessage::BusStatus(status) => {
+                if let Err(err) = timer_tx.send(msg) {
+                    warn!("PlayerManager: failed to re-send message to timer thread! {err}");
+                }
+                self.handle_bus_status(state, status, timer_tx);
+            }
+        };
+
+        // the daemon can't know whether its clients show several players, so it always sends the list
+        if self.args.multi_player || self.args.is_daemon() {
+            self.publish_player_list(state);
         }
     }
 
-    fn handle_metadata_event(
-        &self,
-        players: &mut HashMap<String, PlayerClient>,
-        mpris_metadata: MprisMetadata,
-    ) {
+    fn handle_metadata_event(&self, state: &mut PlayerManagerState, mpris_metadata: MprisMetadata) {
         let player_id = mpris_metadata.player_id.clone();
-        let active_players = self.get_active_player_ids(players);
-        match players.entry(player_id.clone()) {
+        match state.players.entry(player_id.clone()) {
             Entry::Occupied(mut e) => {
                 e.get_mut().update_metadata(mpris_metadata);
                 match self.dbus_client.query_playback_status(&player_id) {
@@ -292,60 +449,45 @@ impl PlayerManager {
             }
         }
 
-        if let Some(p) = players.get(&player_id) {
-            if !p.playing() && active_players.iter().any(|id| id != &p.get_id()) {
-            } else {
-                self.publish_player_state(p, players);
-            }
-        }
+        self.select_active_player(state, &player_id);
     }
 
-    fn handle_playback_event(
-        &self,
-        players: &mut HashMap<String, PlayerClient>,
-        mpris_playback: MprisPlayback,
-    ) {
+    fn handle_playback_event(&self, state: &mut PlayerManagerState, mpris_playback: MprisPlayback) {
         let id = &mpris_playback.player_id.clone();
-        self.query_player_if_not_exists(players, id);
-
-        let active_players = self.get_active_player_ids(players);
+        // like a player we never knew about quitting
+        if !state.players.contains_key(id) && mpris_playback.playing == Some(PlaybackState::Stopped)
+        {
+            return;
+        }
+        self.query_player_if_not_exists(&mut state.players, id);
 
-        if let Some(player) = players.get_mut(id) {
+        if let Some(player) = state.players.get_mut(id) {
             match self.dbus_client.query_metadata(id) {
                 Ok(metadata) => player.update_metadata(metadata),
                 Err(err) => {
                     warn!("PlayerManager::handle_playback_event: failed to query metadata, {err}")
                 }
             }
-            player.update_playback_state(mpris_playback);
         }
+        state.update_playback(mpris_playback);
 
-        if let Some(player) = players.get(id) {
-            // if the latest player is not playing, find the most recent one that is still playing and display that instead
-            if !player.playing() && active_players.iter().any(|id| id != &player.get_id()) {
-                self.set_most_recent_player_as_active(players);
-            } else {
-                self.publish_player_state(player, players);
-            }
+        if state.players.contains_key(id) {
+            self.select_active_player(state, id);
         } else {
             error!("failed to get player during PlaybackState update");
         }
     }
 
-    fn handle_seeked_event(
-        &self,
-        players: &mut HashMap<String, PlayerClient>,
-        mpris_seeked: MprisSeeked,
-    ) {
+    fn handle_seeked_event(&self, state: &mut PlayerManagerState, mpris_seeked: MprisSeeked) {
         let id = &mpris_seeked.player_id;
-        self.query_player_if_not_exists(players, id);
+        self.query_player_if_not_exists(&mut state.players, id);
 
-        if let Some(player) = players.get_mut(id) {
+        if let Some(player) = state.players.get_mut(id) {
             player.update_position(mpris_seeked.position);
         }
 
-        if let Some(player) = players.get(id) {
-            self.publish_player_state(player, players);
+        if let Some(player) = state.players.get(id) {
+            self.publish_player_state(player, state);
         } else {
             error!("failed to get player during Seeked update");
         }
@@ -371,70 +513,271 @@ impl PlayerManager {
         }
     }
 
-    fn set_most_recent_player_as_active(&self, players: &mut HashMap<String, PlayerClient>) {
-        let mut player_id: Option<String> = None;
-        if let Some((_, player)) = players
-            .iter_mut()
-            .filter(|(_, p)| p.playing())
-            .max_by_key(|(_, p)| p.last_updated)
-        {
-            player.last_updated = Instant::now();
-            player_id = Some(player.get_id());
-        };
+    /// Looks for every player once we're connected, as players that are already running won't
+    /// announce themselves until something changes. While the bus is gone nothing is displayed,
+    /// but the players are kept until they're found again, so the pin can follow its player
+    fn handle_bus_status(
+        &self,
+        state: &mut PlayerManagerState,
+        status: BusStatus,
+        timer_tx: &Sender<PlayerManagerMessage>,
+    ) {
+        match status {
+            BusStatus::Disconnected(reason) => {
+                info!("lost the bus: {reason}");
+                state.active = None;
+                state.chosen = None;
+            }
+            BusStatus::Connected => {
+                let found = self.discover_players(timer_tx);
+                state.replace_players(found);
 
-        if let Some(p) = player_id.and_then(|id| players.get(&id)) {
-            self.publish_player_state(p, players)
+                state.active = state.choose_active();
+                if let Some(p) = state.active.as_ref().and_then(|id| state.players.get(id)) {
+                    self.publish_player_state(p, state);
+                }
+            }
         }
     }
 
-    fn get_last_updated_player(
+    fn discover_players(
         &self,
-        players: &HashMap<String, PlayerClient>,
-    ) -> Option<PlayerClient> {
-        players.values().cloned().max_by_key(|p| p.last_updated)
-    }
+        timer_tx: &Sender<PlayerManagerMessage>,
+    ) -> HashMap<String, PlayerClient> {
+        let mut players = HashMap::new();
+        let ids = match self.dbus_client.list_players() {
+            Ok(ids) => ids,
+            Err(err) => {
+                warn!("failed to look for players: {err}");
+                return players;
+            }
+        };
+
+        for id in ids {
+            let identity = match self.dbus_client.query_mediaplayer_identity(&id) {
+                Ok(identity) => identity,
+                Err(err) => {
+                    warn!("failed to query identity of '{id}', skipping it: {err}");
+                    continue;
+                }
+            };
+            if !self.args.is_whitelisted(&identity) {
+                debug!("ignoring '{identity}', not in whitelist");
+                continue;
+            }
+            let metadata = match self.dbus_client.query_metadata(&id) {
+                Ok(metadata) => metadata,
+                Err(err) => {
+                    warn!("failed to query metadata of '{identity}', skipping it: {err}");
+                    continue;
+                }
+            };
+
+            info!("found player '{identity}' ({id})");
+            let mut player = PlayerClient::new(identity, metadata);
+
+            // the timer thread keeps the position going from here on
+            let mut timer_messages = vec![];
+            if let Ok(playback) = self.dbus_client.query_playback_status(&id) {
+                player.update_playback_state(playback.clone());
+                timer_messages.push(PlayerManagerMessage::PlaybackState(playback));
+            }
+            // not every player reports its position
+            if let Ok(position) = self.dbus_client.query_position(&id) {
+                let mut seeked = MprisSeeked::new(id.clone());
+                seeked.position = position.max(0) as u128;
+                player.update_position(seeked.position);
+                timer_messages.push(PlayerManagerMessage::Seeked(seeked));
+            }
+            for msg in timer_messages {
+                if let Err(err) = timer_tx.send(msg) {
+                    warn!("PlayerManager: failed to send message to timer thread! {err}");
+                }
+            }
+
+            players.insert(id, player);
+        }
 
-    fn get_active_player_ids(&self, players: &mut HashMap<String, PlayerClient>) -> Vec<String> {
         players
-            .iter()
-            .filter(|(_, p)| p.playing())
-            .map(|(id, _)| id)
-            .cloned()
-            .collect::<Vec<String>>()
     }
 
-    pub fn publish_player_state(
-        &self,
-        player: &PlayerClient,
-        players: &HashMap<String, PlayerClient>,
-    ) {
-        if self
-            .get_last_updated_player(players)
-            .map(|p| p.get_id() != player.get_id())
-            .is_some_and(|is_not_same| is_not_same)
-        {
+    fn handle_control_command(&self, state: &mut PlayerManagerState, command: ControlCommand) {
+        match command {
+            ControlCommand::Pin => state.pinned = state.active.clone(),
+            ControlCommand::Unpin => {
+                state.pinned = None;
+                state.chosen = None;
+                state.active = state.choose_active();
+            }
+            ControlCommand::NextPlayer => state.cycle_active_player(1),
+            ControlCommand::PreviousPlayer => state.cycle_active_player(-1),
+            ControlCommand::PlayPause
+            | ControlCommand::Play
+            | ControlCommand::Pause
+            | ControlCommand::Next
+            | ControlCommand::Previous => {
+                // the player's new state comes back to us through its own signals
+                self.control_active_player(state, &command);
+                return;
+            }
+        }
+
+        if let Some(p) = state.active.as_ref().and_then(|id| state.players.get(id)) {
+            self.publish_player_state(p, state);
+        }
+    }
+
+    fn control_active_player(&self, state: &PlayerManagerState, command: &ControlCommand) {
+        let Some(id) = state.active.as_deref() else {
+            debug!("no active player to send '{command}' to");
             return;
         };
 
-        match PlayerState::from_mpris_data(
+        let result = match command {
+            ControlCommand::PlayPause => self.dbus_client.play_pause_mpris_player(id),
+            ControlCommand::Play => self.dbus_client.play_mpris_player(id),
+            ControlCommand::Pause => self.dbus_client.pause_mpris_player(id),
+            ControlCommand::Next => self.dbus_client.next_mpris_player(id),
+            ControlCommand::Previous => self.dbus_client.previous_mpris_player(id),
+            _ => Ok(()),
+        };
+
+        if let Err(err) = result {
+            error!("failed to send '{command}' to '{id}': {err}");
+        }
+    }
+
+    /// Decide which player to display after `updated_id` changed.
+    /// Publishes the active player's state if it was either replaced or updated
+    fn select_active_player(&self, state: &mut PlayerManagerState, updated_id: &str) {
+        let previous = state.active.clone();
+        state.active = state.choose_active();
+
+        let Some(active) = &state.active else {
+            return;
+        };
+
+        if previous.as_ref() != Some(active) || active == updated_id {
+            if let Some(p) = state.players.get(active) {
+                self.publish_player_state(p, state);
+            }
+        }
+    }
+
+    fn to_player_state(
+        &self,
+        player: &PlayerClient,
+        state: &PlayerManagerState,
+    ) -> Option<PlayerState> {
+        let player_state = PlayerState::from_mpris_data(
             player.name().to_owned(),
             player.metadata(),
             player.playback_state(),
             player.position(),
-        ) {
-            Some(state) => match bincode::encode_to_vec(state, config::standard()) {
+        );
+
+        if player_state.is_none() {
+            warn!("failed to construct PlayerState. did we get empty metadata? skipping publish: {:?}", player.metadata());
+        }
+
+        player_state.map(|p| p.with_pinned(state.pinned.as_ref() == Some(&player.get_id())))
+    }
+
+    fn publish_player_state(&self, player: &PlayerClient, state: &PlayerManagerState) {
+        if state.active.as_ref() != Some(&player.get_id()) {
+            return;
+        };
+
+        if let Some(player_state) = self.to_player_state(player, state) {
+            match bincode::encode_to_vec(player_state, config::standard()) {
                 Ok(encoded) => self
                     .event_bus
                     .publish(EventType::PlayerStateChanged, encoded),
                 Err(err) => {
                     warn!("failed to encode player state, skipping publish\n\n{err}");
                 }
-            },
-            None => {
-                warn!("failed to construct PlayerState. did we get empty metadata? skipping publish: {:?}", player.metadata());
             }
         }
     }
+
+    /// Every known player, ordered by the selection policy with the active player first
+    fn player_list(&self, state: &PlayerManagerState) -> Vec<PlayerState> {
+        let mut ranked = state.policy.rank(&state.players, state.active.as_deref());
+        if let Some(active) = &state.active {
+            ranked.retain(|id| id != active);
+            ranked.insert(0, active.clone());
+        }
+
+        ranked
+            .iter()
+            .filter_map(|id| state.players.get(id))
+            .filter_map(|p| self.to_player_state(p, state))
+            .collect()
+    }
+
+    fn publish_player_list(&self, state: &PlayerManagerState) {
+        let player_states = self.player_list(state);
+        match bincode::encode_to_vec(player_states, config::standard()) {
+            Ok(encoded) => self
+                .event_bus
+                .publish(EventType::PlayerListChanged, encoded),
+            Err(err) => {
+                warn!("failed to encode player list, skipping publish\n\n{err}");
+            }
+        }
+    }
+
+    /// Handles the events of a `--record`ed session one after the other, in the order they were
+    /// recorded and without waiting between them. `on_change` gets the players to display, active
+    /// first, every time they change. Positions only move with the recorded seeks, as no timer runs
+    pub fn replay(&self, records: &[Record], mut on_change: impl FnMut(&[PlayerState])) {
+        let mut state = PlayerManagerState::new(self.init_policy());
+        // nothing ticks while replaying, the timer's messages are just dropped at the end
+        let (timer_tx, _timer_rx) = mpsc::channel();
+        let mut displayed: Option<Vec<PlayerState>> = None;
+
+        for record in records {
+            let RecordEntry::Event { event_type, data } = &record.entry else {
+                continue;
+            };
+            let msg = match Self::replayed_message(event_type, data) {
+                Ok(Some(msg)) => msg,
+                Ok(None) => continue,
+                Err(err) => {
+                    warn!("failed to decode replayed {event_type}: {err}");
+                    continue;
+                }
+            };
+
+            debug!("replaying {event_type} from {}ms", record.at_ms);
+            self.handle_message(&mut state, msg, &timer_tx);
+
+            let players = self.player_list(&state);
+            if displayed.as_ref() != Some(&players) {
+                on_change(&players);
+                displayed = Some(players);
+            }
+        }
+    }
+
+    fn replayed_message(
+        event_type: &EventType,
+        data: &[u8],
+    ) -> Result<Option<PlayerManagerMessage>, DecodeError> {
+        fn decode<T: bincode::Decode<()>>(data: &[u8]) -> Result<T, DecodeError> {
+            Ok(bincode::decode_from_slice(data, config::standard())?.0)
+        }
+
+        Ok(Some(match event_type {
+            EventType::PlaybackChanged => PlayerManagerMessage::PlaybackState(decode(data)?),
+            EventType::PlayerSongChanged => PlayerManagerMessage::Metadata(decode(data)?),
+            EventType::Seeked => PlayerManagerMessage::Seeked(decode(data)?),
+            EventType::Rate => PlayerManagerMessage::Rate(decode(data)?),
+            EventType::ControlCommand => PlayerManagerMessage::Command(decode(data)?),
+            EventType::BusStatusChanged => PlayerManagerMessage::BusStatus(decode(data)?),
+            _ => return Ok(None),
+        }))
+    }
 }
 
 impl Runnable for PlayerManager {
@@ -446,3 +789,270 @@ impl Runnable for PlayerManager {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use clap::Parser;
+
+    use crate::{
+        event_bus::EventBus,
+        interfaces::player_queries::ReplayedQueries,
+        models::playback_state::PlaybackState,
+        selection::{
+            prefer_playing::PreferPlaying,
+            testing::{player, players},
+        },
+    };
+
+    use super::*;
+
+    fn state(players: HashMap<String, PlayerClient>, active: &str) -> PlayerManagerState {
+        let mut state = PlayerManagerState::new(Box::new(PreferPlaying));
+        state.players = players;
+        state.active = Some(active.to_string());
+        state
+    }
+
+    fn playback(id: &str, playing: PlaybackState) -> MprisPlayback {
+        MprisPlayback::new_with_playing(id.to_string(), Some(playing))
+    }
+
+    #[test]
+    fn player_chosen_by_hand_stays_on_later_events() {
+        let start = Instant::now();
+        let mut state = state(
+            players(vec![
+                player(start, "firefox", Some(PlaybackState::Paused), 0),
+                player(start, "spotify", Some(PlaybackState::Playing), 1),
+            ]),
+            ":1.spotify",
+        );
+
+        state.cycle_active_player(-1);
+        assert_eq!(state.active.as_deref(), Some(":1.firefox"));
+
+        // spotify moving on to its next track doesn't take the bar back
+        state.update_playback(playback(":1.spotify", PlaybackState::Playing));
+        assert_eq!(state.choose_active().as_deref(), Some(":1.firefox"));
+    }
+
+    #[test]
+    fn player_chosen_by_hand_gives_way_once_stopped() {
+        let start = Instant::now();
+        let mut state = state(
+            players(vec![
+                player(start, "firefox", Some(PlaybackState::Paused), 0),
+                player(start, "spotify", Some(PlaybackState::Playing), 1),
+            ]),
+            ":1.spotify",
+        );
+
+        state.cycle_active_player(1);
+        state.update_playback(playback(":1.firefox", PlaybackState::Stopped));
+        assert_eq!(state.choose_active().as_deref(), Some(":1.spotify"));
+        assert_eq!(state.chosen, None);
+    }
+
+    #[test]
+    fn player_chosen_by_hand_gives_way_when_another_starts_playing() {
+        let start = Instant::now();
+        let mut state = state(
+            players(vec![
+                player(start, "firefox", Some(PlaybackState::Paused), 0),
+                player(start, "spotify", Some(PlaybackState::Paused), 1),
+            ]),
+            ":1.spotify",
+        );
+
+        state.cycle_active_player(1);
+        assert_eq!(state.choose_active().as_deref(), Some(":1.firefox"));
+
+        state.update_playback(playback(":1.spotify", PlaybackState::Playing));
+        assert_eq!(state.choose_active().as_deref(), Some(":1.spotify"));
+    }
+
+    #[test]
+    fn pin_follows_its_player_onto_a_restarted_bus() {
+        let start = Instant::now();
+        let mut state = state(
+            players(vec![
+                player(start, "firefox", Some(PlaybackState::Paused), 0),
+                player(start, "spotify", Some(PlaybackState::Playing), 1),
+            ]),
+            ":1.firefox",
+        );
+        state.pinned = Some(String::from(":1.firefox"));
+
+        let mut firefox = player(start, "firefox", Some(PlaybackState::Paused), 0);
+        firefox.update_metadata(MprisMetadata::new(String::from(":1.42")));
+        state.replace_players(players(vec![
+            firefox,
+            player(start, "spotify", Some(PlaybackState::Playing), 1),
+        ]));
+
+        assert_eq!(state.pinned.as_deref(), Some(":1.42"));
+        assert_eq!(state.choose_active().as_deref(), Some(":1.42"));
+    }
+
+    #[test]
+    fn pin_ignores_another_player_taking_its_unique_name() {
+        let start = Instant::now();
+        let mut state = state(
+            players(vec![player(
+                start,
+                "firefox",
+                Some(PlaybackState::Paused),
+                0,
+            )]),
+            ":1.firefox",
+        );
+        state.pinned = Some(String::from(":1.firefox"));
+
+        let mut spotify = player(start, "spotify", Some(PlaybackState::Playing), 1);
+        spotify.update_metadata(MprisMetadata::new(String::from(":1.firefox")));
+        let mut firefox = player(start, "firefox", Some(PlaybackState::Paused), 0);
+        firefox.update_metadata(MprisMetadata::new(String::from(":1.42")));
+        state.replace_players(players(vec![spotify, firefox]));
+
+        assert_eq!(state.pinned.as_deref(), Some(":1.42"));
+    }
+
+    #[test]
+    fn pin_goes_when_its_player_is_not_found_again() {
+        let start = Instant::now();
+        let mut state = state(
+            players(vec![
+                player(start, "firefox", Some(PlaybackState::Paused), 0),
+                player(start, "spotify", Some(PlaybackState::Playing), 1),
+            ]),
+            ":1.firefox",
+        );
+        state.pinned = Some(String::from(":1.firefox"));
+
+        state.replace_players(players(vec![player(
+            start,
+            "spotify",
+            Some(PlaybackState::Playing),
+            1,
+        )]));
+
+        assert_eq!(state.pinned, None);
+        assert_eq!(state.choose_active().as_deref(), Some(":1.spotify"));
+    }
+
+    #[test]
+    fn cycling_while_pinned_moves_the_pin() {
+        let start = Instant::now();
+        let mut state = state(
+            players(vec![
+                player(start, "firefox", Some(PlaybackState::Paused), 0),
+                player(start, "spotify", Some(PlaybackState::Playing), 1),
+            ]),
+            ":1.spotify",
+        );
+        state.pinned = Some(String::from(":1.spotify"));
+
+        state.cycle_active_player(1);
+        assert_eq!(state.pinned.as_deref(), Some(":1.firefox"));
+        assert_eq!(state.chosen, None);
+
+        // a pin holds even when another player starts playing
+        state.update_playback(playback(":1.spotify", PlaybackState::Playing));
+        assert_eq!(state.choose_active().as_deref(), Some(":1.firefox"));
+    }
+
+    /// A recorded session: spotify plays while firefox is paused, then spotify is stopped
+    fn recorded_session() -> Vec<Record> {
+        fn encoded<T: bincode::Encode>(value: T) -> Vec<u8> {
+            bincode::encode_to_vec(value, config::standard()).unwrap()
+        }
+        fn event<T: bincode::Encode>(at_ms: u64, event_type: EventType, value: T) -> Record {
+            Record {
+                at_ms,
+                entry: RecordEntry::Event {
+                    event_type,
+                    data: encoded(value),
+                },
+            }
+        }
+        fn answer<T: bincode::Encode>(at_ms: u64, query: &str, id: &str, value: T) -> Record {
+            Record {
+                at_ms,
+                entry: RecordEntry::Query {
+                    query: query.to_string(),
+                    player_id: id.to_string(),
+                    answer: Ok(encoded(value)),
+                },
+            }
+        }
+        fn track(id: &str, title: &str) -> MprisMetadata {
+            let mut metadata = MprisMetadata::new(id.to_string());
+            metadata.artist = vec![String::from("Artist")];
+            metadata.album = Some(String::from("Album"));
+            metadata.title = Some(title.to_string());
+            metadata
+        }
+
+        vec![
+            event(0, EventType::BusStatusChanged, BusStatus::Connected),
+            answer(1, "players", "", vec![":1.1", ":1.2"]),
+            answer(1, "identity", ":1.1", "firefox"),
+            answer(1, "metadata", ":1.1", track(":1.1", "Video")),
+            answer(
+                1,
+                "playback_status",
+                ":1.1",
+                playback(":1.1", PlaybackState::Paused),
+            ),
+            answer(1, "position", ":1.1", 5_000_000i64),
+            answer(2, "identity", ":1.2", "spotify"),
+            answer(2, "metadata", ":1.2", track(":1.2", "Song")),
+            answer(
+                2,
+                "playback_status",
+                ":1.2",
+                playback(":1.2", PlaybackState::Playing),
+            ),
+            answer(2, "position", ":1.2", 1_000_000i64),
+            event(4000, EventType::Seeked, {
+                let mut seeked = MprisSeeked::new(String::from(":1.2"));
+                seeked.position = 60_000_000;
+                seeked
+            }),
+            event(
+                9000,
+                EventType::PlaybackChanged,
+                playback(":1.2", PlaybackState::Stopped),
+            ),
+            answer(9001, "metadata", ":1.2", track(":1.2", "Song")),
+        ]
+    }
+
+    #[test]
+    fn replay_shows_the_players_in_recorded_order() {
+        let records = recorded_session();
+        let (_, event_bus) = EventBus::new();
+        let player_manager = PlayerManager::new(
+            Arc::new(Args::parse_from(["waybar-module-music"])),
+            event_bus,
+            Arc::new(ReplayedQueries::new(&records)),
+        );
+
+        let mut shown = vec![];
+        player_manager.replay(&records, |players| {
+            let active = &players[0];
+            shown.push((active.player_name.clone(), active.position));
+        });
+
+        assert_eq!(
+            shown,
+            vec![
+                (String::from("spotify"), 1_000_000),
+                (String::from("spotify"), 60_000_000),
+                (String::from("firefox"), 5_000_000),
+            ]
+        );
+    }
+}
@@ -0,0 +1,393 @@
+use std::{
+    sync::{
+        mpsc::{Receiver, RecvTimeoutError},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use bincode::config;
+use log::{debug, error, info, warn};
+
+use crate::{
+    event_bus::{EventBusHandle, EventType},
+    interfaces::{
+        listenbrainz_client::{ListenBrainzClient, SubmitError},
+        play_store::PlayStore,
+    },
+    models::{
+        config::Config, play::Play, play_tracker::PlayTracker, playback_state::PlaybackState,
+        player_state::PlayerState,
+    },
+};
+
+use super::runnable::Runnable;
+
+/// How many queued listens to send per request
+const BATCH_SIZE: usize = 100;
+
+/// Wait this long before retrying an unreachable server, doubling up to the max on every failure
+const MIN_RETRY_DELAY: Duration = Duration::from_secs(30);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(30 * 60);
+
+/// Submits the tracks that were listened to, keeping them queued on disk until the server accepts them
+pub struct Scrobbler {
+    config: Arc<Config>,
+    event_bus: EventBusHandle,
+}
+
+struct RetryQueue {
+    store: PlayStore,
+    plays: Vec<Play>,
+    retry_at: Option<Instant>,
+    retry_delay: Duration,
+    /// Set once the server refuses our token, which only a fixed config and a restart can solve
+    refused: bool,
+}
+
+impl RetryQueue {
+    /// Whether the server is best left alone for now, as every request blocks until it times out
+    fn is_waiting(&self) -> bool {
+        self.refused || self.retry_at.is_some_and(|at| at > Instant::now())
+    }
+
+    fn back_off(&mut self) {
+        self.retry_at = Some(Instant::now() + self.retry_delay);
+        self.retry_delay = (self.retry_delay * 2).min(MAX_RETRY_DELAY);
+    }
+
+    fn reached(&mut self) {
+        self.retry_at = None;
+        self.retry_delay = MIN_RETRY_DELAY;
+    }
+
+    fn handle_error(&mut self, err: SubmitError) {
+        match err {
+            SubmitError::Unauthorized => {
+                error!(
+                    "{err}, keeping {} listens queued until then",
+                    self.plays.len()
+                );
+                self.refused = true;
+            }
+            SubmitError::Unreachable(_) => {
+                warn!(
+                    "{err}, retrying {} listens in {:?}",
+                    self.plays.len(),
+                    self.retry_delay
+                );
+                self.back_off();
+            }
+            SubmitError::Rejected(_) => warn!("{err}"),
+        }
+    }
+}
+
+impl Scrobbler {
+    pub fn new(config: Arc<Config>, event_bus: EventBusHandle) -> Self {
+        Self { config, event_bus }
+    }
+
+    fn init_worker(&self) {
+        if self.config.scrobbler().token.is_empty() {
+            error!("no token set in [scrobbler], not scrobbling");
+            return;
+        }
+
+        let store = match PlayStore::open("scrobble-queue.jsonl") {
+            Ok(store) => store,
+            Err(err) => {
+                error!("failed to open the scrobble queue: {err}");
+                return;
+            }
+        };
+
+        let plays = store.read_all().unwrap_or_else(|err| {
+            error!("failed to read the scrobble queue: {err}");
+            vec![]
+        });
+        if !plays.is_empty() {
+            info!("{} listens are waiting to be submitted", plays.len());
+        }
+
+        let queue = RetryQueue {
+            store,
+            plays,
+            retry_at: None,
+            retry_delay: MIN_RETRY_DELAY,
+            refused: false,
+        };
+
+        match self.event_bus.subscribe(EventType::PlayerStateChanged) {
+            Some(rx) => self.listen_for_updates(rx, queue),
+            None => error!("failed to subscribe to PlayerStateChanged listener"),
+        }
+    }
+
+    fn is_same_track(a: &PlayerState, b: &PlayerState) -> bool {
+        a.player_id == b.player_id
+            && a.artist == b.artist
+            && a.title == b.title
+            && a.album == b.album
+    }
+
+    fn listen_for_updates(&self, rx: Receiver<Vec<u8>>, mut queue: RetryQueue) {
+        let scrobbler = self.config.scrobbler();
+        let client = ListenBrainzClient::new(&scrobbler.endpoint, &scrobbler.token);
+        // the rules used by Last.fm and ListenBrainz, half the track or 4 minutes
+        let mut tracker = PlayTracker::new(50, Duration::from_secs(4 * 60));
+        let mut last_playing_now: Option<PlayerState> = None;
+
+        // submit whatever was left over from last time
+        Scrobbler::flush(&client, &mut queue);
+
+        loop {
+            let msg = match queue.retry_at {
+                Some(retry_at) if !queue.plays.is_empty() => {
+                    rx.recv_timeout(retry_at.saturating_duration_since(Instant::now()))
+                }
+                _ => rx.recv().map_err(|_| RecvTimeoutError::Disconnected),
+            };
+
+            match msg {
+                Ok(encoded) => {
+                    match bincode::decode_from_slice::<PlayerState, _>(
+                        &encoded[..],
+                        config::standard(),
+                    ) {
+                        Ok((state, _)) => {
+                            // the server needs an artist and a title to take a listen
+                            let play = tracker
+                                .update(&state)
+                                .filter(|play| !play.artist.is_empty() && !play.title.is_empty());
+                            if let Some(play) = play {
+                                debug!("queueing listen of '{} - {}'", play.artist, play.title);
+                                if let Err(err) = queue.store.append(&play) {
+                                    error!("failed to write to the scrobble queue: {err}");
+                                }
+                                queue.plays.push(play);
+                            }
+
+                            if scrobbler.now_playing
+                                && !queue.is_waiting()
+                                && state.playing == Some(PlaybackState::Playing)
+                                && state.has_data()
+                                && !last_playing_now
+                                    .as_ref()
+                                    .is_some_and(|p| Scrobbler::is_same_track(p, &state))
+                            {
+                                match client.playing_now(&state) {
+                                    Ok(()) => queue.reached(),
+                                    Err(err) => queue.handle_error(err),
+                                }
+                                last_playing_now = Some(state);
+                            }
+                        }
+                        Err(err) => warn!("failed to decode message in Scrobbler: {err}"),
+                    }
+                }
+                Err(RecvTimeoutError::Timeout) => (),
+                Err(RecvTimeoutError::Disconnected) => {
+                    warn!("Scrobbler lost its PlayerStateChanged subscription");
+                    return;
+                }
+            }
+
+            Scrobbler::flush(&client, &mut queue);
+        }
+    }
+
+    /// Submit the queued listens, unless we're still waiting to retry
+    fn flush(client: &ListenBrainzClient, queue: &mut RetryQueue) {
+        if queue.plays.is_empty() || queue.is_waiting() {
+            return;
+        }
+
+        let queued = queue.plays.len();
+        while !queue.plays.is_empty() {
+            let batch = queue.plays.len().min(BATCH_SIZE);
+            match client.submit_listens(&queue.plays[..batch]) {
+                Ok(()) => {
+                    queue.plays.drain(..batch);
+                    queue.reached();
+                }
+                Err(err @ SubmitError::Rejected(_)) => {
+                    error!("dropping {batch} listens: {err}");
+                    queue.plays.drain(..batch);
+                }
+                Err(err) => {
+                    queue.handle_error(err);
+                    break;
+                }
+            }
+        }
+
+        if queue.plays.len() != queued {
+            debug!("submitted {} listens", queued - queue.plays.len());
+            if let Err(err) = queue.store.replace_all(&queue.plays) {
+                error!("failed to update the scrobble queue: {err}");
+            }
+        }
+    }
+}
+
+impl Runnable for Scrobbler {
+    fn run(self: Arc<Self>) -> JoinHandle<()> {
+        thread::spawn(move || {
+            info!("starting Scrobbler thread");
+            self.init_worker();
+            info!("Scrobbler thread is stopping");
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{BufRead, BufReader, Read, Write},
+        net::TcpListener,
+    };
+
+    use serde_json::Value;
+
+    use super::*;
+
+    /// A stand-in server that answers each request with the next status, and hands back what it got
+    fn serve(statuses: Vec<&'static str>) -> (String, JoinHandle<Vec<(String, Value)>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+
+        let server = thread::spawn(move || {
+            statuses
+                .into_iter()
+                .map(|status| {
+                    let (stream, _) = listener.accept().unwrap();
+                    let mut reader = BufReader::new(stream);
+
+                    let mut headers = vec![];
+                    loop {
+                        let mut line = String::new();
+                        reader.read_line(&mut line).unwrap();
+                        if line.trim().is_empty() {
+                            break;
+                        }
+                        headers.push(line.trim().to_string());
+                    }
+                    let length = headers
+                        .iter()
+                        .find_map(|h| {
+                            h.to_lowercase()
+                                .strip_prefix("content-length: ")?
+                                .parse()
+                                .ok()
+                        })
+                        .unwrap_or(0);
+                    let mut body = vec![0; length];
+                    reader.read_exact(&mut body).unwrap();
+
+                    write!(
+                        reader.get_mut(),
+                        "HTTP/1.1 {status}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                    )
+                    .unwrap();
+
+                    let authorization = headers
+                        .iter()
+                        .find_map(|h| h.strip_prefix("Authorization: "))
+                        .unwrap_or_default()
+                        .to_string();
+                    (authorization, serde_json::from_slice(&body).unwrap())
+                })
+                .collect()
+        });
+
+        (endpoint, server)
+    }
+
+    /// A queue in a file of its own, as the tests run side by side
+    fn queue(name: &str, plays: Vec<Play>) -> RetryQueue {
+        let path = std::env::temp_dir().join(format!(
+            "scrobble-queue-{name}-{}.jsonl",
+            std::process::id()
+        ));
+        RetryQueue {
+            store: PlayStore::at(path),
+            plays,
+            retry_at: None,
+            retry_delay: MIN_RETRY_DELAY,
+            refused: false,
+        }
+    }
+
+    fn play() -> Play {
+        Play {
+            timestamp: 1_700_000_000,
+            player: String::from("spotify"),
+            artist: String::from("Artist"),
+            title: String::from("Song"),
+            album: String::from("Album"),
+            length: 200,
+            listened: 180,
+            url: String::new(),
+        }
+    }
+
+    #[test]
+    fn listens_are_retried_with_backoff_after_a_server_error() {
+        let (endpoint, server) = serve(vec!["503 Service Unavailable", "200 OK"]);
+        let client = ListenBrainzClient::new(&endpoint, "secret");
+        let mut queue = queue("backoff", vec![play()]);
+
+        Scrobbler::flush(&client, &mut queue);
+        assert_eq!(queue.plays, vec![play()]);
+        assert!(queue.retry_at.is_some_and(|at| at > Instant::now()));
+        assert_eq!(queue.retry_delay, MIN_RETRY_DELAY * 2);
+
+        // nothing is sent before it's time to retry
+        Scrobbler::flush(&client, &mut queue);
+        assert_eq!(queue.plays.len(), 1);
+
+        queue.retry_at = Some(Instant::now());
+        Scrobbler::flush(&client, &mut queue);
+        assert!(queue.plays.is_empty());
+        assert_eq!(queue.retry_delay, MIN_RETRY_DELAY);
+        let _ = std::fs::remove_file(queue.store.path());
+
+        let requests = server.join().unwrap();
+        assert_eq!(requests.len(), 2);
+        for (authorization, body) in requests {
+            assert_eq!(authorization, "Token secret");
+            assert_eq!(body["listen_type"], "single");
+            let listen = &body["payload"][0];
+            assert_eq!(listen["listened_at"], 1_700_000_000);
+            assert_eq!(listen["track_metadata"]["artist_name"], "Artist");
+            assert_eq!(listen["track_metadata"]["track_name"], "Song");
+            assert_eq!(listen["track_metadata"]["release_name"], "Album");
+            assert_eq!(
+                listen["track_metadata"]["additional_info"]["media_player"],
+                "spotify"
+            );
+            assert_eq!(
+                listen["track_metadata"]["additional_info"]["duration_ms"],
+                200_000
+            );
+        }
+    }
+
+    #[test]
+    fn refused_token_keeps_the_listens_and_stops_trying() {
+        let (endpoint, server) = serve(vec!["401 Unauthorized"]);
+        let client = ListenBrainzClient::new(&endpoint, "wrong");
+        let mut queue = queue("refused", vec![play()]);
+
+        Scrobbler::flush(&client, &mut queue);
+        assert_eq!(queue.plays, vec![play()]);
+        assert!(queue.refused);
+        assert_eq!(server.join().unwrap().len(), 1);
+
+        // the server is gone now, so another request would fail to connect and back off
+        Scrobbler::flush(&client, &mut queue);
+        assert_eq!(queue.retry_at, None);
+    }
+}
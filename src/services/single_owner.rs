@@ -0,0 +1,74 @@
+use std::{
+    fs::{File, OpenOptions},
+    io,
+    sync::Arc,
+    thread::{self, JoinHandle},
+};
+
+use log::{info, warn};
+
+use crate::helpers;
+
+use super::runnable::Runnable;
+
+/// Runs services that act on what's played, like scrobbling or running hooks, in one instance only.
+/// With a bar on each monitor and no daemon, every bar runs the module, so the first one to take
+/// the lock runs them and the others wait, taking over once it stops
+pub struct SingleOwner {
+    lock_name: &'static str,
+    services: Vec<Arc<dyn Runnable + Send + Sync>>,
+}
+
+impl SingleOwner {
+    pub fn new(lock_name: &'static str, services: Vec<Arc<dyn Runnable + Send + Sync>>) -> Self {
+        Self {
+            lock_name,
+            services,
+        }
+    }
+
+    fn open_lock(&self) -> io::Result<File> {
+        let path = helpers::dir::get_and_create_dir(dirs::cache_dir)?.join(self.lock_name);
+        OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(path)
+    }
+
+    /// Blocks until no other instance holds the lock. It's let go when the file is closed,
+    /// which the OS also does for an instance that crashed
+    fn wait_for_lock(&self) -> io::Result<File> {
+        let file = self.open_lock()?;
+        if file.try_lock().is_err() {
+            info!("another instance acts on what's played, waiting for it to stop");
+            file.lock()?;
+        }
+        Ok(file)
+    }
+}
+
+impl Runnable for SingleOwner {
+    fn run(self: Arc<Self>) -> JoinHandle<()> {
+        thread::spawn(move || {
+            // running them twice beats not running them at all
+            let _lock = match self.wait_for_lock() {
+                Ok(file) => Some(file),
+                Err(err) => {
+                    warn!("failed to take '{}', running anyway: {err}", self.lock_name);
+                    None
+                }
+            };
+
+            info!("running the services that act on what's played");
+            let handles: Vec<JoinHandle<()>> = self
+                .services
+                .iter()
+                .map(|service| service.clone().run())
+                .collect();
+            for handle in handles {
+                let _ = handle.join();
+            }
+        })
+    }
+}
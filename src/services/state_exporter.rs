@@ -0,0 +1,167 @@
+use std::{
+    path::PathBuf,
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+};
+
+use bincode::config;
+use log::{debug, error, info, warn};
+
+use crate::{
+    event_bus::{EventBusHandle, EventType},
+    helpers,
+    models::{
+        cached_art::CachedArt,
+        config::{Config, NowPlayingFormat},
+        now_playing::NowPlaying,
+        player_state::PlayerState,
+    },
+    utils::template,
+};
+
+use super::runnable::Runnable;
+
+enum ExporterMessage {
+    PlayerStateChanged(PlayerState),
+    ArtCached(CachedArt),
+}
+
+/// Writes the displayed player to a file on every change, for programs outside the bar
+pub struct StateExporter {
+    config: Arc<Config>,
+    event_bus: EventBusHandle,
+}
+
+impl StateExporter {
+    pub fn new(config: Arc<Config>, event_bus: EventBusHandle) -> Self {
+        Self { config, event_bus }
+    }
+
+    fn init_worker(&self) {
+        let path = match self.output_path() {
+            Ok(path) => path,
+            Err(err) => {
+                error!("failed to get the now playing file location: {err}");
+                return;
+            }
+        };
+        info!("writing now playing to {path:?}");
+
+        let (tx, rx) = mpsc::channel();
+        self.subscribe_to_event(
+            EventType::PlayerStateChanged,
+            tx.clone(),
+            ExporterMessage::PlayerStateChanged,
+        );
+        self.subscribe_to_event(EventType::ArtCached, tx, ExporterMessage::ArtCached);
+
+        self.listen_for_updates(rx, path);
+    }
+
+    fn output_path(&self) -> Result<PathBuf, std::io::Error> {
+        let export = self.config.now_playing();
+        if let Some(path) = &export.path {
+            return Ok(path.clone());
+        }
+
+        let file_name = match export.format {
+            NowPlayingFormat::Json => "now-playing.json",
+            NowPlayingFormat::Text => "now-playing.txt",
+        };
+        Ok(
+            helpers::dir::get_and_create_dir(|| dirs::runtime_dir().or_else(dirs::cache_dir))?
+                .join(file_name),
+        )
+    }
+
+    fn render(
+        &self,
+        state: &PlayerState,
+        art: Option<&CachedArt>,
+    ) -> Result<String, serde_json::Error> {
+        let export = self.config.now_playing();
+        match export.format {
+            NowPlayingFormat::Json => serde_json::to_string(&NowPlaying::from(state).with_art(art)),
+            NowPlayingFormat::Text if state.is_stopped() => Ok(String::new()),
+            NowPlayingFormat::Text => Ok(template::render(&export.template, state)),
+        }
+    }
+
+    fn subscribe_to_event<T, F>(
+        &self,
+        event_type: EventType,
+        tx: Sender<ExporterMessage>,
+        message_constructor: F,
+    ) where
+        T: bincode::Decode<()>,
+        F: Fn(T) -> ExporterMessage + Send + 'static,
+    {
+        match self.event_bus.subscribe(event_type.clone()) {
+            Some(rx) => {
+                thread::spawn(move || {
+                    for encoded in rx {
+                        match bincode::decode_from_slice(&encoded[..], config::standard()) {
+                            Ok((data, _)) => {
+                                if tx.send(message_constructor(data)).is_err() {
+                                    return;
+                                }
+                            }
+                            Err(err) => warn!("failed to decode message in StateExporter: {err}"),
+                        }
+                    }
+                });
+            }
+            None => error!("failed to subscribe to {event_type} listener"),
+        }
+    }
+
+    fn listen_for_updates(&self, rx: Receiver<ExporterMessage>, path: PathBuf) {
+        let mut state: Option<PlayerState> = None;
+        let mut art: Option<CachedArt> = None;
+        let mut last_written: Option<String> = None;
+
+        for msg in rx {
+            match msg {
+                ExporterMessage::PlayerStateChanged(new_state) => state = Some(new_state),
+                ExporterMessage::ArtCached(cached) => art = Some(cached),
+            }
+
+            let Some(state) = &state else {
+                continue;
+            };
+            // the art may still belong to the previous track, until the new one is cached
+            let art = art.as_ref().filter(|art| art.art_url == state.art_url);
+
+            let contents = match self.render(state, art) {
+                Ok(contents) => contents + "\n",
+                Err(err) => {
+                    error!("failed to serialize now playing: {err}");
+                    continue;
+                }
+            };
+
+            if last_written.as_ref() == Some(&contents) {
+                continue;
+            }
+
+            debug!("writing now playing to {path:?}");
+            match helpers::file::write_atomically(&path, contents.as_bytes()) {
+                Ok(()) => last_written = Some(contents),
+                Err(err) => error!("failed to write now playing to {path:?}: {err}"),
+            }
+        }
+    }
+}
+
+impl Runnable for StateExporter {
+    fn run(self: Arc<Self>) -> JoinHandle<()> {
+        thread::spawn(move || {
+            info!("starting StateExporter thread");
+            self.init_worker();
+            info!("StateExporter thread is stopping");
+        })
+    }
+}
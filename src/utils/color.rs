@@ -0,0 +1,67 @@
+use std::{collections::HashMap, path::Path};
+
+use image::{imageops::FilterType, ImageReader};
+
+use crate::models::art_color::ArtColor;
+
+/// Covers are scaled down to this before looking at their colors, which is plenty for finding the dominant one
+const SAMPLE_SIZE: u32 = 64;
+
+/// Similar colors, grouped together
+#[derive(Default)]
+struct Bucket {
+    /// Colorful pixels count for more, so accents win over large grey areas
+    weight: f32,
+    sum: [u64; 3],
+    count: u64,
+}
+
+/// Find the dominant color of an image, preferring colorful pixels over greys so
+/// a cover with a bit of color on a black background gives that color, not black
+pub fn dominant_color(path: &Path) -> Result<ArtColor, image::ImageError> {
+    // cached art has no file extension, so the format has to come from the contents
+    let image = ImageReader::open(path)?
+        .with_guessed_format()?
+        .decode()?
+        .resize(SAMPLE_SIZE, SAMPLE_SIZE, FilterType::Triangle)
+        .to_rgb8();
+
+    // group similar colors by only looking at the top 4 bits of each channel
+    let mut buckets: HashMap<(u8, u8, u8), Bucket> = HashMap::new();
+    let mut average = [0u64; 3];
+    for pixel in image.pixels() {
+        let [red, green, blue] = pixel.0;
+        average[0] += red as u64;
+        average[1] += green as u64;
+        average[2] += blue as u64;
+
+        let color = ArtColor { red, green, blue };
+        let brightness = red.max(green).max(blue);
+        // very dark pixels have no meaningful color, no matter how saturated they appear
+        if brightness < 40 {
+            continue;
+        }
+
+        let bucket = buckets
+            .entry((red >> 4, green >> 4, blue >> 4))
+            .or_default();
+        bucket.weight += 0.1 + color.saturation();
+        bucket.sum[0] += red as u64;
+        bucket.sum[1] += green as u64;
+        bucket.sum[2] += blue as u64;
+        bucket.count += 1;
+    }
+
+    let pixel_count = (image.width() * image.height()).max(1) as u64;
+    let (sum, count) = buckets
+        .into_values()
+        .max_by(|a, b| a.weight.total_cmp(&b.weight))
+        .map(|bucket| (bucket.sum, bucket.count))
+        .unwrap_or((average, pixel_count));
+
+    Ok(ArtColor {
+        red: (sum[0] / count) as u8,
+        green: (sum[1] / count) as u8,
+        blue: (sum[2] / count) as u8,
+    })
+}
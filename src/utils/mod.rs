@@ -1 +1,4 @@
+pub mod color;
+pub mod template;
 pub mod time;
+pub mod url;
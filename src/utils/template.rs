@@ -0,0 +1,37 @@
+use crate::{
+    models::{playback_state::PlaybackState, player_state::PlayerState},
+    utils::time,
+};
+
+/// Fill in a template like "%artist% - %title%" without any text effects,
+/// for places outside the bar like notifications
+pub fn render(template: &str, state: &PlayerState) -> String {
+    let replacements = [
+        ("artist", state.artist.clone()),
+        ("title", state.title.clone()),
+        ("album", state.album.clone()),
+        ("player", state.player_name.clone()),
+        (
+            "length",
+            time::microseconds_to_formatted_time(state.length as u128),
+        ),
+        (
+            "position",
+            time::microseconds_to_formatted_time(state.position),
+        ),
+        (
+            "status",
+            state
+                .playing
+                .clone()
+                .unwrap_or(PlaybackState::Stopped)
+                .to_string(),
+        ),
+    ];
+
+    replacements
+        .iter()
+        .fold(template.to_string(), |acc, (key, value)| {
+            acc.replace(&format!("%{key}%"), value)
+        })
+}
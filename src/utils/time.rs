@@ -1,5 +1,7 @@
 use std::time::{SystemTime, UNIX_EPOCH};
 
+use chrono::{Days, Local, TimeZone};
+
 pub fn get_current_timestamp() -> u64 {
     SystemTime::now()
         .duration_since(UNIX_EPOCH)
@@ -11,3 +13,22 @@ pub fn microseconds_to_formatted_time(microseconds: u128) -> String {
     let seconds = microseconds / 1000 / 1000;
     format!("{:02}:{:02}", seconds / 60, seconds % 60)
 }
+
+/// UNIX timestamp of local midnight, `days_ago` days before today
+pub fn start_of_local_day(days_ago: u64) -> u64 {
+    let date = Local::now().date_naive() - Days::new(days_ago);
+    date.and_hms_opt(0, 0, 0)
+        .and_then(|midnight| Local.from_local_datetime(&midnight).earliest())
+        .map(|midnight| midnight.timestamp().max(0) as u64)
+        .unwrap_or_default()
+}
+
+/// Longer spans of time, like "2h 05m" or "14m"
+pub fn seconds_to_formatted_duration(seconds: u64) -> String {
+    let minutes = seconds / 60;
+    if minutes >= 60 {
+        format!("{}h {:02}m", minutes / 60, minutes % 60)
+    } else {
+        format!("{minutes}m")
+    }
+}
@@ -0,0 +1,34 @@
+/// Decode `%20` style escapes, leaving anything that isn't a valid escape as is
+pub fn percent_decode(text: &str) -> String {
+    String::from_utf8_lossy(&percent_decode_bytes(text)).into_owned()
+}
+
+/// Like [`percent_decode`], but keeps the raw bytes, for data that isn't text
+pub fn percent_decode_bytes(text: &str) -> Vec<u8> {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            if let Some(byte) = std::str::from_utf8(&bytes[i + 1..i + 3])
+                .ok()
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+            {
+                decoded.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+
+    decoded
+}
+
+/// The local path of a `file://` URL, if it is one
+pub fn file_path(url: &str) -> Option<std::path::PathBuf> {
+    url.strip_prefix("file://")
+        .map(|path| std::path::PathBuf::from(percent_decode(path)))
+}